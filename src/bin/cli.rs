@@ -0,0 +1,91 @@
+use anyhow::{Context, Result, anyhow, bail};
+use claw::{ScrapeReq, scrape_prices, write_csv};
+use std::io::Write;
+
+const USAGE: &str = "usage: claw-cli scrape --url URL [--pages N] [--format json|csv|ndjson]\n\
+    \n\
+    One-shot scrape printed to stdout; logs go to stderr. Reads the same CLAW_* env\n\
+    config as the server.";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+struct Args {
+    url: String,
+    pages: Option<usize>,
+    format: Format,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args> {
+    match args.next().as_deref() {
+        Some("scrape") => {}
+        Some("-h" | "--help") | None => bail!("{USAGE}"),
+        Some(other) => bail!("unknown command {other:?}\n\n{USAGE}"),
+    }
+    let mut url = None;
+    let mut pages = None;
+    let mut format = Format::Json;
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("{flag} needs a value\n\n{USAGE}"))
+        };
+        match flag.as_str() {
+            "--url" => url = Some(value()?),
+            "--pages" => {
+                let v = value()?;
+                pages = Some(
+                    v.parse()
+                        .with_context(|| format!("invalid --pages {v:?}"))?,
+                );
+            }
+            "--format" => {
+                format = match value()?.as_str() {
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    "ndjson" => Format::Ndjson,
+                    other => bail!("unknown --format {other:?}, expected json, csv or ndjson"),
+                }
+            }
+            "-h" | "--help" => bail!("{USAGE}"),
+            other => bail!("unknown flag {other:?}\n\n{USAGE}"),
+        }
+    }
+    let url = url.ok_or_else(|| anyhow!("--url is required\n\n{USAGE}"))?;
+    Ok(Args { url, pages, format })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    let req = ScrapeReq::new(args.url, args.pages);
+    let (hits, meta) = scrape_prices(&req).await?;
+
+    let mut out = std::io::stdout().lock();
+    match args.format {
+        Format::Json => {
+            serde_json::to_writer_pretty(
+                &mut out,
+                &serde_json::json!({ "hits": hits, "meta": meta }),
+            )?;
+            writeln!(out)?;
+        }
+        Format::Ndjson => {
+            for hit in &hits {
+                serde_json::to_writer(&mut out, hit)?;
+                writeln!(out)?;
+            }
+        }
+        Format::Csv => write_csv(&hits, &mut out)?,
+    }
+    out.flush()?;
+    // csv/ndjson have nowhere to put Meta; a summary on stderr keeps stdout clean
+    if args.format != Format::Json {
+        eprintln!("[cli] meta {}", serde_json::to_string(&meta)?);
+    }
+    Ok(())
+}
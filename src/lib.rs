@@ -0,0 +1,6913 @@
+use actix_web::{
+    App, HttpRequest, HttpResponse, HttpServer, Responder, get,
+    http::header::{Accept, Header},
+    post, web,
+};
+use anyhow::{Context, Result, anyhow};
+use rand::{Rng, SeedableRng, rng, rngs::StdRng, seq::SliceRandom};
+use regex::{Regex, RegexBuilder};
+use reqwest::{
+    StatusCode,
+    header::{
+        ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, CACHE_CONTROL, CONNECTION, CONTENT_TYPE, DNT,
+        ETAG, HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        PRAGMA, REFERER, UPGRADE_INSECURE_REQUESTS, USER_AGENT,
+    },
+};
+use robotstxt::DefaultMatcher;
+use rusqlite::{Connection, OptionalExtension};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::{task::yield_now, time::sleep};
+use url::Url;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+// for SSE streaming
+use bytes::Bytes;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc};
+
+// -------------------------
+// Request / Response Types
+// -------------------------
+
+/// Body of POST /scrape; GET /scrape takes the same fields as query params.
+#[derive(Deserialize, ToSchema, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub struct ScrapeReq {
+    /// Category URL, with or without ?page=N. We'll start from that page and auto-iterate.
+    url: String,
+    /// Optional page cap; if omitted we use HARD_PAGE_CAP.
+    page_range: Option<usize>,
+    /// Keep only hits whose title matches this regex (case-insensitive unless `(?-i)`).
+    title_include: Option<String>,
+    /// Drop hits whose title matches this regex (case-insensitive unless `(?-i)`).
+    title_exclude: Option<String>,
+    /// Response shaping: skip this many hits of the finished scrape.
+    offset: Option<usize>,
+    /// Response shaping: return at most this many hits.
+    count: Option<usize>,
+    /// How duplicates are detected; defaults to `id`.
+    #[serde(default)]
+    dedup_mode: DedupMode,
+    /// Keep only new builds (`true`) or only non-new builds (`false`).
+    new_build: Option<bool>,
+    /// Comma-separated listing statuses to drop, e.g. `sold,reserved`; by
+    /// default every hit is kept with its `status` annotated.
+    exclude_status: Option<String>,
+    /// Keep only listings posted on or after this date, `YYYY-MM-DD`. Undated
+    /// hits are kept unless `strict_since`. Lists run newest-first, so a page
+    /// whose every card is older ends the scrape.
+    since: Option<String>,
+    /// With `since`, drop hits whose posted date couldn't be read too;
+    /// `strict_since=1` in a query string.
+    #[serde(default, deserialize_with = "de_flag")]
+    strict_since: bool,
+    /// Keep only hits priced at least this much; unpriced hits are dropped.
+    min_price: Option<f64>,
+    /// Keep only hits priced at most this much; unpriced hits are dropped.
+    max_price: Option<f64>,
+    /// Keep only hits of at least this many m²; hits without sqm are dropped.
+    min_sqm: Option<f64>,
+    /// Keep only hits of at most this many m²; hits without sqm are dropped.
+    max_sqm: Option<f64>,
+    /// Stop after this many consecutive pages with no new cards (default 1).
+    empty_page_tolerance: Option<usize>,
+    /// Comma-separated PriceHit fields to return, e.g. `title,price_numeric,listing_url`.
+    fields: Option<String>,
+    /// Preview mode: first page only, no warmup request. `quick=1` in a query string.
+    #[serde(default, deserialize_with = "de_flag")]
+    quick: bool,
+    /// Referer sent with each category page; defaults to `prev_page`.
+    #[serde(default)]
+    referer_strategy: RefererStrategy,
+    /// Return `{pages: [{page, url, count, hits}], meta}` instead of flat hits.
+    #[serde(default, deserialize_with = "de_flag")]
+    grouped: bool,
+    /// Convert EUR/HRK prices to this currency at the fixed conversion rate;
+    /// price filters then apply to converted prices.
+    convert_to: Option<Currency>,
+    /// Hard bound on the whole scrape; when it runs out the hits so far are
+    /// returned with `meta.timed_out` and `meta.partial`.
+    total_timeout_secs: Option<u64>,
+    /// Scrape everything but return `hits: []` and `meta.aggregates`;
+    /// `include_meta_only=1` in a query string.
+    #[serde(default, deserialize_with = "de_flag")]
+    include_meta_only: bool,
+    /// Accept-Language for every request of this scrape, e.g. `en-US,en;q=0.8`;
+    /// the default is a jittered Croatian-first value.
+    accept_language: Option<String>,
+    /// Fetch the page's own "next" link rather than synthesizing `?page=N+1`,
+    /// for sites with opaque pagination tokens; synthesis stays the fallback.
+    #[serde(default, deserialize_with = "de_flag")]
+    follow_next: bool,
+    /// Dedup session: hits an earlier call in the same session returned are
+    /// left out. Also taken from the `X-Claw-Session` header.
+    session: Option<String>,
+    /// Debugging aid: substring that marks a fetched category page as valid,
+    /// in place of the adapter's (`EntityList-item` on njuskalo).
+    success_marker: Option<String>,
+    /// Add each card's visible text as `raw_text`; `include_text=1` in a query
+    /// string. Large, meant for building datasets and auditing the parser.
+    #[serde(default, deserialize_with = "de_flag")]
+    include_text: bool,
+    /// Shortest pause between pages, overriding CLAW_MIN_DELAY_MS for this
+    /// scrape; never below CLAW_DELAY_FLOOR_MS nor above CLAW_DELAY_CEILING_MS.
+    min_delay_ms: Option<u64>,
+    /// Longest pause between pages, overriding CLAW_MAX_DELAY_MS for this
+    /// scrape; never above CLAW_DELAY_CEILING_MS.
+    max_delay_ms: Option<u64>,
+    /// Burst pacing: after this many pages take a long rest instead of the
+    /// usual pause; 0 paces every page alike. Defaults to CLAW_BURST_SIZE.
+    burst_size: Option<usize>,
+    /// Length of the rest between bursts, ±20%; defaults to CLAW_BURST_REST_MS,
+    /// at most ten minutes.
+    burst_rest_ms: Option<u64>,
+    /// Sampling: parse only the first this-many cards of each page; 0 means no limit.
+    max_per_page: Option<usize>,
+    /// Drop hits without a numeric price ("Cijena na upit"), no bounds needed;
+    /// `only_priced=1` in a query string.
+    #[serde(default, deserialize_with = "de_flag")]
+    only_priced: bool,
+    /// Stop as soon as this many hits survived the filters, even mid-page;
+    /// "20 matching flats" without walking every page. 0 means no target.
+    stop_after_hits: Option<usize>,
+    /// Leave null fields out of each hit; `compact=1` in a query string.
+    #[serde(default, deserialize_with = "de_flag")]
+    compact: bool,
+}
+
+impl ScrapeReq {
+    /// A plain scrape of `url`, every other option at its default; what the CLI runs.
+    pub fn new(url: impl Into<String>, page_range: Option<usize>) -> Self {
+        Self {
+            url: url.into(),
+            page_range,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Clone, Copy, PartialEq, Eq, Debug)]
+enum Currency {
+    #[serde(rename = "EUR", alias = "eur")]
+    Eur,
+    #[serde(rename = "HRK", alias = "hrk")]
+    Hrk,
+}
+
+/// The fixed kuna-euro conversion rate of the 2023 changeover.
+const HRK_PER_EUR: f64 = 7.5345;
+
+impl Currency {
+    fn code(self) -> &'static str {
+        match self {
+            Currency::Eur => "EUR",
+            Currency::Hrk => "HRK",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "EUR" => Some(Currency::Eur),
+            "HRK" => Some(Currency::Hrk),
+            _ => None,
+        }
+    }
+
+    /// Multiplier taking an amount in `self` to `to`.
+    fn rate_to(self, to: Currency) -> f64 {
+        match (self, to) {
+            (Currency::Hrk, Currency::Eur) => 1.0 / HRK_PER_EUR,
+            (Currency::Eur, Currency::Hrk) => HRK_PER_EUR,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Tradeoffs: `prev_page` looks like someone clicking "next" and is the most
+/// natural for deep paging. `origin` is steady but odd past page 1, nobody
+/// reaches page 7 straight from the home page. `search_engine` looks like organic
+/// arrival on every page, which suits one or two pages but is implausible for a
+/// long run, and flips Sec-Fetch-Site to cross-site.
+#[derive(Deserialize, Serialize, ToSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum RefererStrategy {
+    /// Previous category page; the site origin for the first page.
+    #[default]
+    PrevPage,
+    /// Always the site origin.
+    Origin,
+    /// Always a Google search referer.
+    SearchEngine,
+}
+
+const SEARCH_ENGINE_REFERER: &str = "https://www.google.com/";
+
+impl RefererStrategy {
+    fn referer(self, prev_page: Option<&Url>, origin: &str) -> String {
+        match (self, prev_page) {
+            (RefererStrategy::PrevPage, Some(prev)) => prev.to_string(),
+            (RefererStrategy::PrevPage, None) | (RefererStrategy::Origin, _) => origin.to_string(),
+            (RefererStrategy::SearchEngine, _) => SEARCH_ENGINE_REFERER.to_string(),
+        }
+    }
+}
+
+/// A bool that also takes the query-string spellings `1`/`0`, `yes`/`no`, `on`/`off`.
+fn de_flag<'de, D: serde::Deserializer<'de>>(d: D) -> std::result::Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Text(String),
+    }
+    match Flag::deserialize(d)? {
+        Flag::Bool(b) => Ok(b),
+        Flag::Text(t) => match t.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" | "" => Ok(false),
+            other => Err(serde::de::Error::custom(format!(
+                "invalid flag value {other:?}"
+            ))),
+        },
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum DedupMode {
+    /// Same listing id.
+    #[default]
+    Id,
+    /// Same normalized (title, price_numeric, sqm), catches reposts under a new id.
+    Content,
+    /// Either of the above.
+    Both,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+pub struct PriceHit {
+    pub id: String,
+    /// Category page the hit was found on.
+    pub page: usize,
+    pub listing_url: String,
+    pub title: String,
+    pub price_numeric: Option<f64>,
+    /// How price_numeric was obtained, see the CONFIDENCE_* constants.
+    pub price_confidence: Option<f64>,
+    pub currency: Option<String>,
+    pub raw_price: String,
+    /// "monthly" for rents ("850 €/mj", "mjesečno"); price_per_m2 is left
+    /// out for periodic prices.
+    pub price_period: Option<String>,
+    /// Struck-through pre-discount price, when the card shows one above price_numeric.
+    pub original_price: Option<f64>,
+    /// (original_price - price_numeric) / original_price * 100, one decimal.
+    pub discount_pct: Option<f64>,
+    pub sqm: Option<f64>,
+    /// How sqm was obtained, see the CONFIDENCE_* constants.
+    pub sqm_confidence: Option<f64>,
+    /// Rooms from the "Broj soba" attribute; 2.5 for "2,5".
+    pub rooms: Option<f64>,
+    /// The "Kat" attribute as the site words it, e.g. "3. kat", "Prizemlje".
+    pub floor: Option<String>,
+    /// District the "Lokacija" attribute (else the title) maps to in the
+    /// CLAW_DISTRICTS_PATH table; None without a table or a match.
+    pub district: Option<String>,
+    /// Labeled "Key: value" lines of the card description, keys as shown.
+    pub attributes: BTreeMap<String, String>,
+    pub price_per_m2: Option<f64>,
+    /// price_per_m2 rounded half away from zero; what the dashboard and CSV show.
+    pub price_per_m2_rounded: Option<i64>,
+    /// price_numeric formatted for CLAW_LOCALE, e.g. "185.000 €".
+    pub price_display: Option<String>,
+    /// price_per_m2_rounded formatted for CLAW_LOCALE, e.g. "3.190 €/m²".
+    pub price_per_m2_display: Option<String>,
+    /// New construction ("novogradnja"). A badge/label on the card wins; the
+    /// title mentioning it is the fallback and sets `new_build_from_title`.
+    pub new_build: bool,
+    /// Photos in the listing: the card's gallery-count badge if it has one,
+    /// else the images in the card. None when the card shows neither.
+    pub image_count: Option<usize>,
+    /// "active", or "reserved"/"sold" when the card carries a
+    /// "Rezervirano"/"Prodano" badge (CLAW_STATUS_BADGE_SELECTOR).
+    pub status: Option<String>,
+    /// When the listing was posted, `YYYY-MM-DD`, from the card's `<time
+    /// datetime>` or its "dd.mm.yyyy." publish date.
+    pub posted_date: Option<String>,
+    /// Extraction caveats, see the FLAG_* constants.
+    pub flags: Vec<String>,
+    /// The card's visible text, whitespace collapsed; only with `include_text`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_text: Option<String>,
+}
+
+/// Value read from a machine-readable attribute.
+const CONFIDENCE_ATTRIBUTE: f64 = 1.0;
+/// Value parsed from text next to its unit (`€`, `kn`, `m²`).
+const CONFIDENCE_UNIT_MATCHED: f64 = 0.8;
+/// First number in the text, no unit to back it up.
+const CONFIDENCE_FALLBACK: f64 = 0.3;
+
+/// sqm came from a bare number in the description, with no m²/m2 unit next to it.
+const FLAG_SQM_INFERRED: &str = "sqm_inferred";
+/// Price text has no digits ("Cijena na upit", "Po dogovoru", ...).
+const FLAG_PRICE_ON_REQUEST: &str = "price_on_request";
+/// No currency marker in the price text; EUR was assumed.
+const FLAG_CURRENCY_DEFAULTED: &str = "currency_defaulted";
+/// No numeric id in the listing URL; id is a hash of the URL instead.
+const FLAG_ID_HASHED: &str = "id_hashed";
+/// price_numeric came from a machine-readable attribute (`data-price`, ...).
+const FLAG_PRICE_FROM_ATTRIBUTE: &str = "price_from_attribute";
+/// price_numeric was parsed from the visible price text.
+const FLAG_PRICE_FROM_TEXT: &str = "price_from_text";
+/// Prices were converted from the card's currency (`convert_to`); raw_price
+/// still shows the original.
+const FLAG_CURRENCY_CONVERTED: &str = "currency_converted";
+/// new_build comes from the title only; the card had no new-build badge.
+const FLAG_NEW_BUILD_FROM_TITLE: &str = "new_build_from_title";
+
+#[derive(Serialize, ToSchema, Default)]
+pub struct Meta {
+    /// When the scrape finished, RFC 3339 UTC.
+    scraped_at: Option<String>,
+    page_count: usize,
+    total_hits: usize,
+    /// Result count the first page's header claims ("1.234 oglasa"); compare
+    /// with total_hits to judge whether a larger page_range would find more.
+    total_listings: Option<usize>,
+    next_url: Option<String>,
+    /// title_include pattern that was applied, if any.
+    title_include: Option<String>,
+    /// title_exclude pattern that was applied, if any.
+    title_exclude: Option<String>,
+    /// new_build filter that was applied, if any.
+    new_build: Option<bool>,
+    /// Consecutive empty pages that end the scrape.
+    empty_page_tolerance: usize,
+    /// Pages with no new cards seen along the way, including the final ones.
+    empty_pages: usize,
+    /// The first page was the site's "no results" page: the search itself is
+    /// empty, as opposed to a scrape that found nothing.
+    no_results: bool,
+    /// Pages the server answered 304 Not Modified for; their hits come from the page cache.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unchanged_pages: Vec<usize>,
+    /// Offset to request the next slice with, when offset/count left hits out.
+    next_offset: Option<usize>,
+    dedup_mode: DedupMode,
+    /// Hits dropped because their id was already seen.
+    dropped_duplicate_id: usize,
+    /// Hits dropped because an identical (title, price, sqm) was already seen.
+    dropped_duplicate_content: usize,
+    /// Hits dropped because an earlier call in the same `session` returned them.
+    dropped_session_seen: usize,
+    /// Hits dropped by `only_priced` for lacking a numeric price.
+    dropped_unpriced: usize,
+    /// Hits per currency as found on the cards, before any `convert_to`;
+    /// defaulted (no marker on the card) and unpriced hits count as `unknown`.
+    currency_breakdown: BTreeMap<String, usize>,
+    /// total_timeout_secs ran out before the scrape finished.
+    timed_out: bool,
+    /// Hits are what was gathered before the scrape was cut short; next_url
+    /// points at the first page not fetched.
+    partial: bool,
+    /// Pause drawn between pages, after min/max_delay_ms and the server floor.
+    delay_ms: Option<DelayRange>,
+    /// max_per_page that was applied, if any.
+    max_per_page: Option<usize>,
+    /// since date that was applied, if any.
+    since: Option<String>,
+    /// The scrape ended early on a page whose listings were all posted
+    /// before `since`.
+    since_cutoff: bool,
+    /// stop_after_hits that was applied, if any.
+    stop_after_hits: Option<usize>,
+    /// The scrape ended because stop_after_hits was reached; page_count is
+    /// then the pages it took.
+    target_reached: bool,
+    /// Burst pacing in effect, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    burst: Option<Burst>,
+    /// Summary statistics over every hit, filled in for `include_meta_only`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aggregates: Option<Aggregates>,
+    /// Non-fatal request problems, e.g. unknown names in `fields`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    /// (page, url) of every fetched page, in order; feeds `grouped` output.
+    #[serde(skip)]
+    fetched_pages: Vec<(usize, String)>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Aggregates {
+    /// Hits with a price_numeric.
+    priced: usize,
+    /// Hits with an sqm.
+    with_sqm: usize,
+    price: Option<Stats>,
+    price_per_m2: Option<Stats>,
+    sqm: Option<Stats>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Stats {
+    min: f64,
+    max: f64,
+    mean: f64,
+    median: f64,
+}
+
+impl Stats {
+    /// None for an empty set.
+    fn of(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let n = values.len();
+        let median = if n % 2 == 1 {
+            values[n / 2]
+        } else {
+            (values[n / 2 - 1] + values[n / 2]) / 2.0
+        };
+        Some(Stats {
+            min: values[0],
+            max: values[n - 1],
+            mean: values.iter().sum::<f64>() / n as f64,
+            median,
+        })
+    }
+}
+
+fn aggregate(hits: &[PriceHit]) -> Aggregates {
+    let prices: Vec<f64> = hits.iter().filter_map(|h| h.price_numeric).collect();
+    let sqms: Vec<f64> = hits.iter().filter_map(|h| h.sqm).collect();
+    Aggregates {
+        priced: prices.len(),
+        with_sqm: sqms.len(),
+        price: Stats::of(prices),
+        price_per_m2: Stats::of(hits.iter().filter_map(|h| h.price_per_m2).collect()),
+        sqm: Stats::of(sqms),
+    }
+}
+
+/// One fetched page of a `grouped` response.
+#[derive(Serialize, ToSchema)]
+struct PageGroup {
+    page: usize,
+    url: String,
+    count: usize,
+    hits: Vec<PriceHit>,
+}
+
+/// Buckets hits under the pages they came from, keeping pages that ended up
+/// with none so the structure matches what was fetched.
+fn group_by_page(hits: Vec<PriceHit>, fetched_pages: &[(usize, String)]) -> Vec<PageGroup> {
+    let mut groups: Vec<PageGroup> = fetched_pages
+        .iter()
+        .map(|(page, url)| PageGroup {
+            page: *page,
+            url: url.clone(),
+            count: 0,
+            hits: Vec::new(),
+        })
+        .collect();
+    for hit in hits {
+        if let Some(g) = groups.iter_mut().find(|g| g.page == hit.page) {
+            g.count += 1;
+            g.hits.push(hit);
+        }
+    }
+    groups
+}
+
+#[derive(Serialize, ToSchema)]
+struct ApiResponse {
+    hits: Vec<PriceHit>,
+    meta: Meta,
+}
+
+#[derive(Serialize, ToSchema, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+enum ChangeType {
+    /// Id not in the history before this run.
+    New,
+    PriceDrop,
+    PriceRise,
+}
+
+/// A hit that differs from the stored history.
+#[derive(Serialize, ToSchema)]
+struct HitChange {
+    change_type: ChangeType,
+    /// Last stored price, for price_drop/price_rise.
+    old_price: Option<f64>,
+    #[serde(flatten)]
+    hit: PriceHit,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ChangesResponse {
+    hits: Vec<HitChange>,
+    meta: Meta,
+}
+
+/// What GET /listing extracts from a single listing's detail page.
+#[derive(Serialize, ToSchema)]
+struct ListingDetail {
+    id: String,
+    url: String,
+    title: String,
+    raw_price: String,
+    price_numeric: Option<f64>,
+    currency: Option<String>,
+    /// Living area from the details table ("Stambena površina").
+    area_m2: Option<f64>,
+    description: String,
+    /// Gallery image URLs, absolute and in page order.
+    images: Vec<String>,
+    /// Label → value pairs of the basic details table, as shown on the page.
+    details: Vec<(String, String)>,
+}
+
+// -------------------------
+// HTTP Handlers
+// -------------------------
+
+/// Machine-readable twin of the text banner served by `/`.
+const INDEX_ENDPOINTS: &[(&str, &str, &str)] = &[
+    ("POST", "/scrape", "Scrape listing pages"),
+    ("GET", "/scrape", "Scrape listing pages (query parameters)"),
+    (
+        "GET",
+        "/scrape.xlsx",
+        "Scrape listing pages as an Excel workbook",
+    ),
+    (
+        "GET",
+        "/scrape.csv",
+        "Scrape listing pages as CSV, streamed page by page",
+    ),
+    (
+        "POST",
+        "/scrape/pages",
+        "Scrape an explicit list of page URLs",
+    ),
+    (
+        "GET",
+        "/scrape/new",
+        "Hits not seen in earlier scrapes (CLAW_DB_PATH)",
+    ),
+    (
+        "GET",
+        "/scrape/profile/{name}",
+        "Scrape with a saved search profile (CLAW_PROFILES_PATH)",
+    ),
+    ("GET", "/listing", "Single listing detail"),
+    ("GET", "/pages", "Pagination info for a search URL"),
+    ("GET", "/scrape/estimate", "Projected requests and duration"),
+    ("GET", "/scrape/stream", "Scrape as server-sent events"),
+    ("GET", "/scrape/ws", "Scrape over a WebSocket, cancellable"),
+    ("GET", "/robots", "robots.txt decision for a URL"),
+    (
+        "POST",
+        "/admin/cache/clear",
+        "Empty in-process caches (CLAW_API_TOKEN)",
+    ),
+    (
+        "GET",
+        "/config",
+        "Effective runtime configuration (CLAW_API_TOKEN)",
+    ),
+    ("GET", "/dashboard", "Browser UI"),
+    ("GET", "/openapi.json", "OpenAPI document"),
+    ("GET", "/healthz", "Liveness probe"),
+];
+
+/// True when the client ranks a JSON media type above everything else;
+/// browsers (text/html first) and curl (`*/*`) keep the text banner.
+fn prefers_json(req: &HttpRequest) -> bool {
+    Accept::parse(req)
+        .ok()
+        .and_then(|a| a.ranked().into_iter().next())
+        .is_some_and(|m| m.subtype() == "json" || m.suffix().is_some_and(|s| s == "json"))
+}
+
+#[get("/")]
+async fn index(req: HttpRequest) -> impl Responder {
+    if prefers_json(&req) {
+        let endpoints: Vec<_> = INDEX_ENDPOINTS
+            .iter()
+            .map(|(method, path, description)| {
+                serde_json::json!({
+                    "method": method,
+                    "path": format!("{}{}", config().base_path, path),
+                    "description": description,
+                })
+            })
+            .collect();
+        return HttpResponse::Ok().json(serde_json::json!({
+            "service": "claw",
+            "version": env!("CARGO_PKG_VERSION"),
+            "endpoints": endpoints,
+        }));
+    }
+    HttpResponse::Ok().body(
+        "Claw online.\n\
+         JSON:\n  POST /scrape {\"url\":\"https://www.njuskalo.hr/prodaja-stanova/zagreb\",\"page_range\":10}\n  GET  /scrape?url=...&page_range=10\n  GET  /scrape.xlsx?url=...&page_range=10 (Excel)\n  GET  /scrape.csv?url=...&page_range=10 (CSV, streamed)\n  POST /scrape/pages {\"urls\":[\"...?page=1\",\"...?page=2\"]}\n\
+         History (CLAW_DB_PATH):\n  GET  /scrape/new?url=...&page_range=10\n\
+         Profiles (CLAW_PROFILES_PATH):\n  GET  /scrape/profile/{name}\n\
+         Listing:\n  GET  /listing?url=...-oglas-123\n\
+         Pagination:\n  GET  /pages?url=...\n  GET  /scrape/estimate?url=...&page_range=10\n\
+         Robots:\n  GET  /robots?url=...\n\
+         Admin (CLAW_API_TOKEN):\n  POST /admin/cache/clear\n  GET  /config\n\
+         Stream:\n  GET  /scrape/stream?url=...&page_range=10 (SSE)\n  GET  /scrape/ws (WebSocket; send {\"url\":\"...\",\"page_range\":10}, {\"cmd\":\"cancel\"} stops)\n\
+         UI:\n  GET  /dashboard\n\
+         Docs:\n  GET  /openapi.json",
+    )
+}
+
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+#[utoipa::path(
+    post,
+    path = "/scrape",
+    request_body = ScrapeReq,
+    responses(
+        (status = 200, description = "Scraped hits", body = ApiResponse),
+        (status = 400, description = "Invalid URL, domain not allowed, robots.txt disallow or fetch failure"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[post("/scrape")]
+async fn scrape_endpoint(http: HttpRequest, body: web::Json<ScrapeReq>) -> impl Responder {
+    let mut req = body.into_inner();
+    req.session = req.session.or_else(|| session_header(&http));
+    scrape_json(&req, client_id(&http)).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape",
+    params(ScrapeReq),
+    responses(
+        (status = 200, description = "Scraped hits", body = ApiResponse),
+        (status = 400, description = "Invalid URL, domain not allowed, robots.txt disallow or fetch failure"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/scrape")]
+async fn scrape_get(http: HttpRequest, q: web::Query<ScrapeReq>) -> impl Responder {
+    let mut req = q.into_inner();
+    req.session = req.session.or_else(|| session_header(&http));
+    scrape_json(&req, client_id(&http)).await
+}
+
+const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+#[utoipa::path(
+    get,
+    path = "/scrape.xlsx",
+    params(ScrapeReq),
+    responses(
+        (status = 200, description = "Scraped hits as an Excel workbook", content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        (status = 400, description = "Invalid URL, domain not allowed, robots.txt disallow or fetch failure"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/scrape.xlsx")]
+async fn scrape_xlsx(http: HttpRequest, q: web::Query<ScrapeReq>) -> impl Responder {
+    let mut req = q.into_inner();
+    req.session = req.session.or_else(|| session_header(&http));
+    let _permit = match scrape_slot(&req.url, client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    let result = async {
+        let (hits, mut meta) = scrape_prices(&req).await?;
+        let hits = slice_hits(hits, req.offset, req.count, &mut meta);
+        let host = Url::parse(&req.url)?
+            .host_str()
+            .unwrap_or("claw")
+            .to_string();
+        let date = time::OffsetDateTime::now_utc().date();
+        let body = write_xlsx(&hits)?;
+        session_claim(req.session.as_deref(), &hits);
+        Ok::<_, anyhow::Error>((body, format!("{host}-{date}.xlsx")))
+    }
+    .await;
+    match result {
+        Ok((body, filename)) => HttpResponse::Ok()
+            .insert_header(("Content-Type", XLSX_CONTENT_TYPE))
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{filename}\""),
+            ))
+            .body(body),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// Runs the scrape and shapes the JSON response shared by POST and GET /scrape.
+/// X-Claw-Pages, X-Claw-Hits and X-Claw-Duration-Ms summarize the run for
+/// clients and logs that don't read the body.
+async fn scrape_json(req: &ScrapeReq, client: Option<String>) -> HttpResponse {
+    let _permit = match scrape_slot(&req.url, client).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    let started = Instant::now();
+    match scrape_prices(req).await {
+        Ok((hits, meta)) => {
+            let summary = [
+                ("x-claw-pages", meta.page_count as u64),
+                ("x-claw-hits", meta.total_hits as u64),
+                ("x-claw-duration-ms", started.elapsed().as_millis() as u64),
+            ];
+            let mut rsp = shape_response(req, hits, meta);
+            for (name, value) in summary {
+                rsp.headers_mut().insert(
+                    actix_web::http::header::HeaderName::from_static(name),
+                    value.into(),
+                );
+            }
+            rsp
+        }
+        Err(e) => error_response(&e),
+    }
+}
+
+/// The body of a successful /scrape: `include_meta_only`, offset/count,
+/// `grouped` and `fields` applied in that order.
+fn shape_response(req: &ScrapeReq, hits: Vec<PriceHit>, mut meta: Meta) -> HttpResponse {
+    // aggregates cover the whole scrape, offset/count don't apply
+    if req.include_meta_only {
+        meta.aggregates = Some(aggregate(&hits));
+        return hits_response(
+            req.compact,
+            ApiResponse {
+                hits: Vec::new(),
+                meta,
+            },
+        );
+    }
+    let hits = slice_hits(hits, req.offset, req.count, &mut meta);
+    session_claim(req.session.as_deref(), &hits);
+    if req.grouped {
+        let groups = group_by_page(hits, &meta.fetched_pages);
+        return match req.fields.as_deref() {
+            Some(fields) => {
+                let keep = parse_fields(fields, req.include_text, &mut meta);
+                let pages: Vec<serde_json::Value> = groups
+                    .iter()
+                    .map(|g| {
+                        serde_json::json!({
+                            "page": g.page,
+                            "url": g.url,
+                            "count": g.count,
+                            "hits": project_fields(&g.hits, &keep),
+                        })
+                    })
+                    .collect();
+                hits_response(
+                    req.compact,
+                    serde_json::json!({ "pages": pages, "meta": meta }),
+                )
+            }
+            None => hits_response(
+                req.compact,
+                serde_json::json!({ "pages": groups, "meta": meta }),
+            ),
+        };
+    }
+    match req.fields.as_deref() {
+        Some(fields) => {
+            let keep = parse_fields(fields, req.include_text, &mut meta);
+            let hits = project_fields(&hits, &keep);
+            hits_response(
+                req.compact,
+                serde_json::json!({ "hits": hits, "meta": meta }),
+            )
+        }
+        None => hits_response(req.compact, ApiResponse { hits, meta }),
+    }
+}
+
+/// 200 with `body` as JSON; `compact` drops null fields from its hits, both
+/// `hits` and `pages[].hits`. Meta keeps its nulls.
+fn hits_response(compact: bool, body: impl Serialize) -> HttpResponse {
+    if !compact {
+        return HttpResponse::Ok().json(body);
+    }
+    let mut value = serde_json::to_value(body).unwrap_or_default();
+    // get_mut, not IndexMut: indexing a missing key would insert it as null
+    if let Some(pages) = value.get_mut("pages").and_then(|p| p.as_array_mut()) {
+        for page in pages {
+            if let Some(hits) = page.get_mut("hits").and_then(|h| h.as_array_mut()) {
+                drop_nulls(hits);
+            }
+        }
+    }
+    if let Some(hits) = value.get_mut("hits").and_then(|h| h.as_array_mut()) {
+        drop_nulls(hits);
+    }
+    HttpResponse::Ok().json(value)
+}
+
+fn drop_nulls(hits: &mut [serde_json::Value]) {
+    for hit in hits {
+        if let Some(map) = hit.as_object_mut() {
+            map.retain(|_, v| !v.is_null());
+        }
+    }
+}
+
+/// Maps a failed scrape to its JSON error: 500 for our own misconfiguration
+/// (ScrapeError::Config), 429 when the host blocked us (HostCooldown), 503
+/// while its circuit breaker is open (CircuitOpen), 400 for everything else.
+fn error_response(e: &anyhow::Error) -> HttpResponse {
+    if let Some(c) = e.downcast_ref::<HostCooldown>() {
+        return cooldown_response(c);
+    }
+    if let Some(c) = e.downcast_ref::<CircuitOpen>() {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header(("Retry-After", retry_after_secs(c.retry_after).to_string()))
+            .json(serde_json::json!({ "error": c.to_string() }));
+    }
+    let err = serde_json::json!({ "error": format!("{e:#}") });
+    match e.downcast_ref::<ScrapeError>() {
+        Some(ScrapeError::Config { .. }) => HttpResponse::InternalServerError().json(err),
+        None => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Applies offset/count to an already complete result; Meta keeps the full
+/// total_hits and gets next_offset when hits remain past the slice.
+fn slice_hits(
+    hits: Vec<PriceHit>,
+    offset: Option<usize>,
+    count: Option<usize>,
+    meta: &mut Meta,
+) -> Vec<PriceHit> {
+    if offset.is_none() && count.is_none() {
+        return hits;
+    }
+    let total = hits.len();
+    let start = offset.unwrap_or(0).min(total);
+    let end = count.map_or(total, |c| start.saturating_add(c).min(total));
+    meta.next_offset = (end < total).then_some(end);
+    hits.into_iter().skip(start).take(end - start).collect()
+}
+
+/// Serialized PriceHit keys, what `fields` may name; also the CSV and XLSX
+/// columns. `raw_text` is left out of those, see TEXT_FIELD.
+const PRICE_HIT_FIELDS: &[&str] = &[
+    "id",
+    "page",
+    "listing_url",
+    "title",
+    "price_numeric",
+    "price_confidence",
+    "currency",
+    "raw_price",
+    "price_period",
+    "original_price",
+    "discount_pct",
+    "sqm",
+    "sqm_confidence",
+    "rooms",
+    "floor",
+    "district",
+    "attributes",
+    "price_per_m2",
+    "price_per_m2_rounded",
+    "price_display",
+    "price_per_m2_display",
+    "new_build",
+    "image_count",
+    "status",
+    "posted_date",
+    "flags",
+];
+
+/// PriceHit.raw_text: a valid `fields` entry only with `include_text`, the
+/// one scrape that fills it.
+const TEXT_FIELD: &str = "raw_text";
+
+/// The known names in a `fields` list; unknown ones become Meta warnings.
+fn parse_fields(fields: &str, include_text: bool, meta: &mut Meta) -> Vec<&'static str> {
+    let mut keep: Vec<&'static str> = Vec::new();
+    for f in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        match PRICE_HIT_FIELDS.iter().find(|known| **known == f) {
+            Some(known) => keep.push(known),
+            None if f == TEXT_FIELD && include_text => keep.push(TEXT_FIELD),
+            None if f == TEXT_FIELD => meta
+                .warnings
+                .push(format!("field {f:?} ignored, it needs include_text")),
+            None => meta.warnings.push(format!("unknown field {f:?} ignored")),
+        }
+    }
+    keep
+}
+
+/// Hits as CSV: a header of PRICE_HIT_FIELDS, then one row per hit; flags
+/// are joined with `;`, missing values are empty cells.
+pub fn write_csv(hits: &[PriceHit], out: &mut impl std::io::Write) -> std::io::Result<()> {
+    writeln!(out, "{}", PRICE_HIT_FIELDS.join(","))?;
+    for hit in hits {
+        writeln!(
+            out,
+            "{}",
+            csv_row(&serde_json::to_value(hit).unwrap_or_default())
+        )?;
+    }
+    Ok(())
+}
+
+/// One CSV line (no newline) for a serialized PriceHit.
+fn csv_row(hit: &serde_json::Value) -> String {
+    PRICE_HIT_FIELDS
+        .iter()
+        .map(|field| csv_cell(&hit[*field]))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Hits as an XLSX workbook, columns as in write_csv: a bold frozen header
+/// with a filter, numbers as numbers so Excel sorts them, listing_url as a
+/// link, flags joined with `;`, attributes as JSON, missing values empty.
+pub fn write_xlsx(hits: &[PriceHit]) -> Result<Vec<u8>> {
+    use rust_xlsxwriter::{Color, Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet().set_name("hits")?;
+    let header = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xDDEBF7));
+    for (col, field) in PRICE_HIT_FIELDS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *field, &header)?;
+    }
+    for (i, hit) in hits.iter().enumerate() {
+        let row = i as u32 + 1;
+        let value = serde_json::to_value(hit)?;
+        for (col, field) in PRICE_HIT_FIELDS.iter().enumerate() {
+            let col = col as u16;
+            match &value[*field] {
+                serde_json::Value::Null => {}
+                serde_json::Value::Number(n) => {
+                    sheet.write_number(row, col, n.as_f64().unwrap_or_default())?;
+                }
+                serde_json::Value::Bool(b) => {
+                    sheet.write_boolean(row, col, *b)?;
+                }
+                serde_json::Value::String(s) if *field == "listing_url" && !s.is_empty() => {
+                    sheet.write_url(row, col, s.as_str())?;
+                }
+                serde_json::Value::String(s) => {
+                    sheet.write_string(row, col, s)?;
+                }
+                serde_json::Value::Array(items) => {
+                    let joined: Vec<String> = items
+                        .iter()
+                        .map(|i| i.as_str().map_or_else(|| i.to_string(), str::to_string))
+                        .collect();
+                    sheet.write_string(row, col, joined.join(";"))?;
+                }
+                other => {
+                    sheet.write_string(row, col, other.to_string())?;
+                }
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, hits.len() as u32, PRICE_HIT_FIELDS.len() as u16 - 1)?;
+    sheet.autofit();
+    Ok(workbook.save_to_buffer()?)
+}
+
+fn csv_cell(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|i| i.as_str().map_or_else(|| i.to_string(), str::to_string))
+            .collect::<Vec<_>>()
+            .join(";"),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Keeps only the `keep` keys of each hit.
+fn project_fields(
+    hits: &[PriceHit],
+    keep: &[&str],
+) -> Vec<serde_json::Map<String, serde_json::Value>> {
+    hits.iter()
+        .map(|hit| match serde_json::to_value(hit) {
+            Ok(serde_json::Value::Object(mut map)) => {
+                map.retain(|k, _| keep.contains(&k.as_str()));
+                map
+            }
+            _ => serde_json::Map::new(),
+        })
+        .collect()
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ScrapePagesReq {
+    /// Exact page URLs to fetch, in order; no pagination is derived. All on one site.
+    urls: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/scrape/pages",
+    request_body = ScrapePagesReq,
+    responses(
+        (status = 200, description = "Combined, deduplicated hits of the given pages", body = ApiResponse),
+        (status = 400, description = "A URL is invalid, not allowed, on a different site than the first, or failed to fetch"),
+        (status = 429, description = "The site is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[post("/scrape/pages")]
+async fn scrape_pages_endpoint(
+    http: HttpRequest,
+    body: web::Json<ScrapePagesReq>,
+) -> impl Responder {
+    // every URL is checked before queueing, so the turn is for the site they share
+    if let Err(e) = check_page_urls(&body.urls) {
+        return error_response(&e);
+    }
+    let _permit = match scrape_slot(&body.urls[0], client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    match scrape_pages(&body.urls).await {
+        Ok((hits, meta)) => HttpResponse::Ok().json(ApiResponse { hits, meta }),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListingQuery {
+    /// Listing detail URL (…-oglas-<id>).
+    url: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/listing",
+    params(ListingQuery),
+    responses(
+        (status = 200, description = "Fields parsed from the listing detail page", body = ListingDetail),
+        (status = 400, description = "Invalid URL, domain not allowed, robots.txt disallow or fetch failure"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/listing")]
+async fn listing_endpoint(http: HttpRequest, q: web::Query<ListingQuery>) -> impl Responder {
+    let _permit = match scrape_slot(&q.url, client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    match scrape_listing(&q.url).await {
+        Ok(detail) => HttpResponse::Ok().json(detail),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PagesQuery {
+    /// Category URL; the page it points at (default 1) is the one fetched.
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PaginationInfo {
+    /// Highest page number in the pager, or derived from total_listings / per_page.
+    total_pages: Option<usize>,
+    /// Result count the category header advertises.
+    total_listings: Option<usize>,
+    /// Cards on the fetched page.
+    per_page: Option<usize>,
+    /// total_pages was derived or guessed rather than read from the pager.
+    estimated: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/pages",
+    params(PagesQuery),
+    responses(
+        (status = 200, description = "Pagination read from one category page", body = PaginationInfo),
+        (status = 400, description = "Invalid URL, domain not allowed, robots.txt disallow or fetch failure"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/pages")]
+async fn pages_endpoint(http: HttpRequest, q: web::Query<PagesQuery>) -> impl Responder {
+    let _permit = match scrape_slot(&q.url, client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    match scrape_pagination(&q.url).await {
+        Ok(info) => HttpResponse::Ok().json(info),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EstimateQuery {
+    /// Category URL, with or without ?page=N.
+    url: String,
+    /// Pages the scrape would be capped at; HARD_PAGE_CAP when omitted.
+    page_range: Option<usize>,
+    /// Fetch the first page to read the real page count (one request).
+    #[serde(default, deserialize_with = "de_flag")]
+    discover: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ScrapeEstimate {
+    /// Category pages the scrape is expected to fetch.
+    pages: usize,
+    /// Where `pages` came from: page_range, pagination or hard_cap.
+    pages_source: &'static str,
+    /// robots.txt + warmup + pages * ESTIMATE_FETCHES_PER_PAGE, rounded up.
+    estimated_requests: usize,
+    /// Fetch time plus the inter-page delay midpoint, rounded up.
+    estimated_seconds: u64,
+    /// Midpoint of the politeness delay between pages.
+    delay_ms_midpoint: u64,
+}
+
+/// Average fetches per page including retries, from watching real runs.
+const ESTIMATE_FETCHES_PER_PAGE: f64 = 1.2;
+/// Typical time one category page fetch takes.
+const ESTIMATE_FETCH_MS: u64 = 1500;
+
+#[utoipa::path(
+    get,
+    path = "/scrape/estimate",
+    params(EstimateQuery),
+    responses(
+        (status = 200, description = "Projected request count and duration; nothing is scraped", body = ScrapeEstimate),
+        (status = 400, description = "Invalid URL, or discovery failed"),
+        (status = 429, description = "The host is cooling down after blocking us (discover=1 only)"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site (discover=1 only)")
+    )
+)]
+#[get("/scrape/estimate")]
+async fn scrape_estimate(http: HttpRequest, q: web::Query<EstimateQuery>) -> impl Responder {
+    let url = match Url::parse(&q.url) {
+        Ok(u) => u,
+        Err(e) => return error_response(&anyhow::Error::new(e).context("invalid url")),
+    };
+    let (_, start_page) = normalize_pager(&url);
+    let (mut pages, mut pages_source) = match q.page_range {
+        Some(n) => (n, "page_range"),
+        None => (HARD_PAGE_CAP, "hard_cap"),
+    };
+    if q.discover {
+        let _permit = match scrape_slot(&q.url, client_id(&http)).await {
+            Ok(p) => p,
+            Err(busy) => return busy,
+        };
+        match scrape_pagination(&q.url).await {
+            Ok(PaginationInfo {
+                total_pages: Some(total),
+                ..
+            }) => {
+                let remaining = total.saturating_sub(start_page) + 1;
+                if remaining < pages {
+                    pages = remaining;
+                    pages_source = "pagination";
+                }
+            }
+            Ok(_) => {}
+            Err(e) => return error_response(&e),
+        }
+    }
+    HttpResponse::Ok().json(estimate(pages, pages_source))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RobotsQuery {
+    /// URL to check; its host must be whitelisted.
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct RobotsReport {
+    robots_url: String,
+    /// robots.txt was downloaded; when false everything is allowed.
+    fetched: bool,
+    /// The verdict ensure_allowed reaches for this URL.
+    allowed: bool,
+    /// User-agent token the rules are matched against.
+    agent: &'static str,
+    /// The Allow/Disallow line behind the verdict, if any rule matched.
+    matched_rule: Option<String>,
+    /// 1-based line of matched_rule in robots.txt.
+    matched_line: Option<usize>,
+    /// Crawl-delay of the group that applies to `agent`, in seconds.
+    crawl_delay: Option<f64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots",
+    params(RobotsQuery),
+    responses(
+        (status = 200, description = "robots.txt decision for the URL, as a scrape would make it", body = RobotsReport),
+        (status = 400, description = "Invalid URL or domain not allowed")
+    )
+)]
+#[get("/robots")]
+async fn robots_endpoint(q: web::Query<RobotsQuery>) -> impl Responder {
+    let url = match Url::parse(&q.url) {
+        Ok(u) => u,
+        Err(e) => return error_response(&anyhow::Error::new(e).context("invalid url")),
+    };
+    let Some(host) = url.host_str() else {
+        return error_response(&AccessDenied::NoHost.into());
+    };
+    if adapter_for_host(host).is_none() {
+        return error_response(&AccessDenied::DomainNotAllowed.into());
+    }
+    let robots = fetch_robots(url.scheme(), host).await;
+    let body = robots.as_deref().unwrap_or("");
+    let allowed = robots_allows(body, url.as_str());
+    // rules match path and query, like the matcher does
+    let rule = robots_rule(body, &url[url::Position::BeforePath..], allowed);
+    HttpResponse::Ok().json(RobotsReport {
+        robots_url: format!("{}://{}/robots.txt", url.scheme(), host),
+        fetched: robots.is_some(),
+        allowed,
+        agent: ROBOTS_AGENT,
+        matched_line: rule.as_ref().map(|r| r.line),
+        matched_rule: rule.map(|r| r.text),
+        crawl_delay: robots_crawl_delay(body),
+    })
+}
+
+/// Projects at most HARD_PAGE_CAP pages, so a huge page_range can't
+/// overflow the arithmetic.
+fn estimate(pages: usize, pages_source: &'static str) -> ScrapeEstimate {
+    let (pages, pages_source) = if pages > HARD_PAGE_CAP {
+        (HARD_PAGE_CAP, "hard_cap")
+    } else {
+        (pages, pages_source)
+    };
+    let delay_ms_midpoint = DelayRange::configured().midpoint();
+    let fetches = (pages as f64 * ESTIMATE_FETCHES_PER_PAGE).ceil() as usize;
+    let ms = (pages as u64)
+        .saturating_mul(ESTIMATE_FETCH_MS)
+        .saturating_add((pages.saturating_sub(1) as u64).saturating_mul(delay_ms_midpoint))
+        .saturating_add(ESTIMATE_FETCH_MS); // warmup
+    ScrapeEstimate {
+        pages,
+        pages_source,
+        estimated_requests: fetches.saturating_add(2),
+        estimated_seconds: ms.div_ceil(1000),
+        delay_ms_midpoint,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape/new",
+    params(ScrapeReq),
+    responses(
+        (status = 200, description = "Hits that are new or changed price since the last /scrape/new run", body = ChangesResponse),
+        (status = 400, description = "Invalid request or scrape failure"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "Listing history is not configured (CLAW_DB_PATH), or the host queue is full")
+    )
+)]
+#[get("/scrape/new")]
+async fn scrape_new(http: HttpRequest, q: web::Query<ScrapeReq>) -> impl Responder {
+    if let Err(e) = history() {
+        let err = serde_json::json!({ "error": format!("{e:#}") });
+        return HttpResponse::ServiceUnavailable().json(err);
+    }
+    let _permit = match scrape_slot(&q.url, client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    let result = async {
+        let (hits, meta) = scrape_prices(&q).await?;
+        let changes = tokio::task::spawn_blocking(move || diff_and_record(hits)).await??;
+        let returned: Vec<PriceHit> = changes.iter().map(|c| c.hit.clone()).collect();
+        session_claim(q.session.as_deref(), &returned);
+        Ok::<_, anyhow::Error>(ChangesResponse {
+            hits: changes,
+            meta,
+        })
+    }
+    .await;
+    match result {
+        Ok(rsp) => HttpResponse::Ok().json(rsp),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape/profile/{name}",
+    params(("name" = String, Path, description = "Profile name in CLAW_PROFILES_PATH")),
+    responses(
+        (status = 200, description = "Scraped hits, as GET /scrape with the profile's params", body = ApiResponse),
+        (status = 400, description = "Invalid URL, domain not allowed, robots.txt disallow or fetch failure"),
+        (status = 404, description = "No such profile, or profiles are not configured (CLAW_PROFILES_PATH)"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 500, description = "Unreadable profiles file or invalid profile"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/scrape/profile/{name}")]
+async fn scrape_profile(http: HttpRequest, name: web::Path<String>) -> impl Responder {
+    let mut req = match load_profile(&name) {
+        Ok(Some(req)) => req,
+        Ok(None) => {
+            let err =
+                serde_json::json!({ "error": format!("no profile named {:?}", name.as_str()) });
+            return HttpResponse::NotFound().json(err);
+        }
+        Err(e) => {
+            eprintln!("[profile] {e:#}");
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": format!("{e:#}") }));
+        }
+    };
+    req.session = req.session.or_else(|| session_header(&http));
+    scrape_json(&req, client_id(&http)).await
+}
+
+/// Looks `name` up in CLAW_PROFILES_PATH, a JSON object of profiles like
+/// `{"zagreb-2room": {"url": "...", "page_range": 10, "filters": {"max_price": 200000}}}`.
+/// Keys under `filters` are ScrapeReq fields too; they sit beside `url` once
+/// loaded, so any ScrapeReq field may go in either place. Ok(None) when the
+/// profile, or the whole file setting, doesn't exist.
+fn load_profile(name: &str) -> Result<Option<ScrapeReq>> {
+    let Some(path) = &config().profiles_path else {
+        return Ok(None);
+    };
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("reading profiles {}", path.display()))?;
+    let mut profiles: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&text)
+        .with_context(|| format!("parsing profiles {}", path.display()))?;
+    let mut profile = match profiles.remove(name) {
+        Some(serde_json::Value::Object(profile)) => profile,
+        Some(_) => return Err(anyhow!("profile {name:?} must be an object")),
+        None => return Ok(None),
+    };
+    if let Some(filters) = profile.remove("filters") {
+        let serde_json::Value::Object(filters) = filters else {
+            return Err(anyhow!("profile {name:?}: filters must be an object"));
+        };
+        profile.extend(filters);
+    }
+    serde_json::from_value(serde_json::Value::Object(profile))
+        .map(Some)
+        .with_context(|| format!("profile {name:?}"))
+}
+
+static SCRAPE_SLOTS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+
+/// Held for the whole scrape: a turn in the target site's queue plus a global slot.
+struct ScrapeSlot {
+    _client_turn: Option<OwnedSemaphorePermit>,
+    _turn: Option<OwnedSemaphorePermit>,
+    _slot: OwnedSemaphorePermit,
+}
+
+/// Refuses a site that is cooling down after a block (429 with Retry-After).
+/// Waits for a turn in the site's FIFO queue (503 when the queue is full), then,
+/// still holding the turn, for one of the CLAW_MAX_CONCURRENT_SCRAPES global
+/// slots. Caps outbound load on the target across all actix workers. The queue
+/// is per adapter, so www. and bare hosts share one CLAW_HOST_CONCURRENCY.
+/// With a `client` (CLAW_CLIENT_HOST_CONCURRENCY), a turn in that client's own
+/// lane for the site comes first, so one caller can't fill the site queue.
+async fn scrape_slot(raw_url: &str, client: Option<String>) -> Result<ScrapeSlot, HttpResponse> {
+    // unparsable or not whitelisted: no queue entry, the scrape itself reports it
+    let site = Url::parse(raw_url)
+        .ok()
+        .and_then(|u| whitelisted(&u).ok())
+        .map(|(_, adapter)| adapter.hosts[0]);
+    if let Some(site) = site
+        && let Some(retry_after) = host_cooldown_left(site)
+    {
+        return Err(cooldown_response(&HostCooldown {
+            host: site.to_string(),
+            retry_after,
+        }));
+    }
+    let client_turn = match (site, client) {
+        (Some(site), Some(client)) => Some(
+            host_turn(
+                &format!("{site} for {client}"),
+                config().client_host_concurrency,
+            )
+            .await?,
+        ),
+        _ => None,
+    };
+    let turn = match site {
+        Some(site) => Some(host_turn(site, config().host_concurrency).await?),
+        None => None,
+    };
+    let slot = SCRAPE_SLOTS
+        .get_or_init(|| Arc::new(Semaphore::new(config().max_concurrent_scrapes)))
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|_| {
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "scrape slots closed" }))
+        })?;
+    Ok(ScrapeSlot {
+        _client_turn: client_turn,
+        _turn: turn,
+        _slot: slot,
+    })
+}
+
+/// Who is asking, for per-client lanes: the first X-Forwarded-For hop when
+/// CLAW_TRUST_PROXY is set (it's spoofable otherwise), else the peer address.
+/// None while CLAW_CLIENT_HOST_CONCURRENCY is 0 (lanes off).
+fn client_id(req: &HttpRequest) -> Option<String> {
+    if config().client_host_concurrency == 0 {
+        return None;
+    }
+    let forwarded = config()
+        .trust_proxy
+        .then(|| {
+            req.headers()
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        })
+        .flatten();
+    forwarded.or_else(|| req.peer_addr().map(|a| a.ip().to_string()))
+}
+
+/// One line per site (or site and client). tokio's Semaphore grants permits in acquire order, which
+/// is what makes the queue first come, first served.
+struct HostQueue {
+    turns: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+static HOST_QUEUES: OnceLock<Mutex<HashMap<String, Arc<HostQueue>>>> = OnceLock::new();
+
+/// Decrements the waiting count however the wait ends, including the client
+/// hanging up while still in line.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+async fn host_turn(host: &str, concurrency: usize) -> Result<OwnedSemaphorePermit, HttpResponse> {
+    let queue = {
+        let mut queues = HOST_QUEUES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        // forget idle lines (nobody waiting, no turn out) so per-client lanes don't pile up
+        queues.retain(|_, q| Arc::strong_count(q) > 1 || Arc::strong_count(&q.turns) > 1);
+        queues
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                Arc::new(HostQueue {
+                    turns: Arc::new(Semaphore::new(concurrency)),
+                    waiting: AtomicUsize::new(0),
+                })
+            })
+            .clone()
+    };
+    // free turn and nobody ahead of us: no queueing
+    if let Ok(turn) = queue.turns.clone().try_acquire_owned() {
+        return Ok(turn);
+    }
+    if queue.waiting.fetch_add(1, Ordering::SeqCst) >= config().host_queue_depth {
+        queue.waiting.fetch_sub(1, Ordering::SeqCst);
+        return Err(HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": format!("scrape queue for {host} is full, try again later")
+        })));
+    }
+    let _waiting = Waiting(&queue.waiting);
+    eprintln!(
+        "[queue] {} waiting for a turn ({} in line)",
+        host,
+        queue.waiting.load(Ordering::SeqCst)
+    );
+    queue.turns.clone().acquire_owned().await.map_err(|_| {
+        HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "scrape queue closed" }))
+    })
+}
+
+// --------------
+// SSE streaming
+// --------------
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct StreamParams {
+    /// Category URL, with or without ?page=N.
+    url: String,
+    /// Optional page cap; if omitted we use HARD_PAGE_CAP.
+    page_range: Option<usize>,
+    /// Same as ScrapeReq.title_include.
+    title_include: Option<String>,
+    /// Same as ScrapeReq.title_exclude.
+    title_exclude: Option<String>,
+    /// Same as ScrapeReq.new_build.
+    new_build: Option<bool>,
+    /// Same as ScrapeReq.exclude_status.
+    exclude_status: Option<String>,
+    /// Same as ScrapeReq.min_price.
+    min_price: Option<f64>,
+    /// Same as ScrapeReq.max_price.
+    max_price: Option<f64>,
+    /// Same as ScrapeReq.min_sqm.
+    min_sqm: Option<f64>,
+    /// Same as ScrapeReq.max_sqm.
+    max_sqm: Option<f64>,
+    /// `live` (default) streams pages as they arrive; `collect` buffers everything,
+    /// dedups and sorts it, then streams the final set in `hits` chunks.
+    #[serde(default)]
+    mode: StreamMode,
+    /// Ascending sort key for `mode=collect`; fetch order when omitted.
+    sort: Option<SortKey>,
+    /// Same as ScrapeReq.total_timeout_secs; on expiry a `timeout` event precedes `done`.
+    total_timeout_secs: Option<u64>,
+    /// Same as ScrapeReq.session.
+    session: Option<String>,
+    /// Consecutive pages without cards, or blocked or rejected, that end the
+    /// stream (default 1).
+    empty_page_tolerance: Option<usize>,
+}
+
+#[derive(Deserialize, ToSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum StreamMode {
+    #[default]
+    Live,
+    Collect,
+}
+
+#[derive(Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum SortKey {
+    Price,
+    PricePerM2,
+    Sqm,
+}
+
+impl SortKey {
+    fn value(self, hit: &PriceHit) -> Option<f64> {
+        match self {
+            SortKey::Price => hit.price_numeric,
+            SortKey::PricePerM2 => hit.price_per_m2,
+            SortKey::Sqm => hit.sqm,
+        }
+    }
+
+    /// Stable ascending sort; hits without the value go last in fetch order.
+    fn sort(self, hits: &mut [PriceHit]) {
+        hits.sort_by(|a, b| match (self.value(a), self.value(b)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+}
+
+/// Hits per `hits` event in `mode=collect`.
+const COLLECT_CHUNK: usize = 50;
+
+/// `data_json` must be a single line, which serde_json output always is; build
+/// payloads with it rather than format! so quotes and newlines get escaped.
+fn sse_event(event: &str, data_json: &str) -> Bytes {
+    let payload = format!("event: {}\ndata: {}\n\n", event, data_json);
+    Bytes::from(payload)
+}
+
+/// One message of a scrape stream, before the transport frames it: an SSE
+/// event on /scrape/stream, a JSON text frame on /scrape/ws.
+struct StreamEvent {
+    event: &'static str,
+    data_json: String,
+}
+
+impl StreamEvent {
+    fn sse(&self) -> Bytes {
+        sse_event(self.event, &self.data_json)
+    }
+
+    /// `{"event": "page", "data": {...}}`; event names are plain identifiers,
+    /// so only the data needs escaping, and serde_json already did it.
+    fn ws_frame(&self) -> String {
+        format!(
+            "{{\"event\":\"{}\",\"data\":{}}}",
+            self.event, self.data_json
+        )
+    }
+}
+
+/// The stream receiver was dropped (browser tab closed, connection reset,
+/// WebSocket `cancel`).
+struct ClientGone;
+
+async fn send_event(
+    tx: &mpsc::Sender<StreamEvent>,
+    event: &'static str,
+    data_json: &str,
+) -> Result<(), ClientGone> {
+    tx.send(StreamEvent {
+        event,
+        data_json: data_json.to_string(),
+    })
+    .await
+    .map_err(|_| ClientGone)
+}
+
+/// Payload of an SSE `error` event. `code` is stable so clients can branch on
+/// it: invalid_url, invalid_filter, domain_not_allowed, path_not_allowed,
+/// robots_disallowed, fetch_failed, host_cooldown, circuit_open, config,
+/// internal, too_many_streams; on /scrape/ws also invalid_start and busy.
+#[derive(Serialize)]
+struct SseError {
+    code: &'static str,
+    message: String,
+}
+
+impl StreamEvent {
+    fn error(code: &'static str, err: impl std::fmt::Display) -> Self {
+        let payload = SseError {
+            code,
+            message: format!("{err:#}"),
+        };
+        StreamEvent {
+            event: "error",
+            data_json: serde_json::to_string(&payload).unwrap_or_default(),
+        }
+    }
+}
+
+/// The bytes /scrape/stream sends for an `error` event.
+pub fn sse_error_event(code: &'static str, message: &str) -> Bytes {
+    StreamEvent::error(code, message).sse()
+}
+
+async fn send_error(
+    tx: &mpsc::Sender<StreamEvent>,
+    code: &'static str,
+    err: impl std::fmt::Display,
+) -> Result<(), ClientGone> {
+    tx.send(StreamEvent::error(code, err))
+        .await
+        .map_err(|_| ClientGone)
+}
+
+static OPEN_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+/// One of the CLAW_MAX_SSE_STREAMS producer tasks; given back on drop, however
+/// the task ends.
+struct StreamSlot;
+
+impl StreamSlot {
+    fn take() -> Option<StreamSlot> {
+        let max = config().max_sse_streams;
+        OPEN_STREAMS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| StreamSlot)
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        OPEN_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Producer side of /scrape/stream and /scrape/ws. Returns Err as soon as the
+/// client is gone so we stop fetching pages nobody will receive.
+async fn run_stream(
+    tx: &mpsc::Sender<StreamEvent>,
+    params: &StreamParams,
+) -> Result<(), ClientGone> {
+    // validate once
+    let parsed = match Url::parse(&params.url) {
+        Ok(u) => u,
+        Err(e) => return send_error(tx, "invalid_url", e).await,
+    };
+    let hit_filter = match HitFilter::for_stream(params) {
+        Ok(f) => f,
+        Err(e) => return send_error(tx, "invalid_filter", e).await,
+    };
+    let (host, adapter) = match ensure_allowed(&parsed).await {
+        Ok(site) => site,
+        Err(e) => {
+            let code = e
+                .downcast_ref::<AccessDenied>()
+                .map_or("fetch_failed", AccessDenied::code);
+            return send_error(tx, code, e).await;
+        }
+    };
+
+    let (base, mut page) = normalize_pager(&parsed);
+    let origin = format!("{}://{}", base.scheme(), host);
+    let mut prev_page_url: Option<Url> = None;
+
+    let max_pages = params.page_range.unwrap_or(HARD_PAGE_CAP);
+    let deadline = scrape_deadline(params.total_timeout_secs);
+    let mut timed_out = false;
+    send_event(
+        tx,
+        "start",
+        &serde_json::json!({ "origin": origin, "max_pages": max_pages }).to_string(),
+    )
+    .await?;
+
+    let selectors = match CardSelectors::new() {
+        Ok(s) => s,
+        Err(e) => return send_error(tx, "config", e).await,
+    };
+
+    // one client per job: the warmup's session cookies carry into every page
+    let client = match build_client() {
+        Ok(c) => c,
+        Err(e) => return send_error(tx, "internal", e).await,
+    };
+    let token = tokio::select! {
+        t = warmup_hit(&client, &origin, FetchOpts::default(), adapter) => t,
+        _ = until(deadline) => None,
+        _ = tx.closed() => return Err(ClientGone),
+    };
+    let fetch_opts = FetchOpts::default().with_token(token.as_ref());
+
+    let mut pages = 0usize;
+    let mut total_hits = 0usize;
+    // mode=collect: everything kept so far, deduped by id
+    let collect = params.mode == StreamMode::Collect;
+    let mut collected: Vec<PriceHit> = Vec::new();
+    let mut seen = SeenHits::new(DedupMode::Id);
+    // ids this session already got; page events add theirs as they go out
+    let mut session_seen = params.session.as_deref().map(session_seen);
+    let empty_page_tolerance = params.empty_page_tolerance.unwrap_or(1).max(1);
+    let mut empty_streak = 0usize;
+    // streams pace by the server's settings, CLAW_BURST_SIZE included
+    let configured = DelayRange::configured();
+    let burst = Burst::for_request(None, None, configured, &mut Vec::new());
+    // streams have no meta.warnings, unknown processor names are only logged
+    let mut chain_warnings: Vec<String> = Vec::new();
+    let processors = hit_processor_chain(&mut chain_warnings);
+    for w in chain_warnings {
+        eprintln!("[stream] {w}");
+    }
+
+    loop {
+        if pages >= max_pages {
+            break;
+        }
+
+        let page_url = match build_page_url(&base, page) {
+            Ok(u) => u,
+            Err(e) => return send_error(tx, "invalid_url", e).await,
+        };
+
+        let referer = prev_page_url
+            .as_ref()
+            .map(|u| u.as_str().to_string())
+            .unwrap_or_else(|| origin.clone());
+
+        // don't sit in a slow fetch for a client that already left
+        let fetch_started = Instant::now();
+        let fetched = tokio::select! {
+            r = retry_fetch_html(&client, &page_url, &referer, &adapter.category_page, fetch_opts) => r,
+            _ = until(deadline) => {
+                timed_out = true;
+                break;
+            }
+            _ = tx.closed() => return Err(ClientGone),
+        };
+        let fetched = match fetched {
+            Ok(f) => f,
+            Err(e) if counts_as_empty_page(&e) && empty_streak + 1 < empty_page_tolerance => {
+                pages += 1;
+                empty_streak += 1;
+                eprintln!(
+                    "[pager] page {} failed, empty page {}/{}, carrying on: {e:#}",
+                    page, empty_streak, empty_page_tolerance
+                );
+                prev_page_url = Some(page_url);
+                page += 1;
+                if pages < max_pages {
+                    tokio::select! {
+                        _ = sleep(Burst::pause_after(burst, pages, configured)) => {}
+                        _ = until(deadline) => {}
+                        _ = tx.closed() => return Err(ClientGone),
+                    }
+                }
+                continue;
+            }
+            Err(e) if e.is::<HostCooldown>() => return send_error(tx, "host_cooldown", e).await,
+            Err(e) if e.is::<CircuitOpen>() => return send_error(tx, "circuit_open", e).await,
+            Err(e) => return send_error(tx, "fetch_failed", e).await,
+        };
+        pages += 1;
+        // logged only, the page event's final_url shows the redirect
+        page_param_dropped(&page_url, &fetched.final_url);
+
+        let fetch_ms = fetch_started.elapsed().as_millis() as u64;
+
+        let parse_started = Instant::now();
+        let doc = Html::parse_document(&fetched.html);
+        let total_listings = if pages == 1 {
+            extract_total_listings(&doc)
+        } else {
+            None
+        };
+        let cards = parse_page_cards(&doc, &page_url, page, &selectors, ParseOpts::default());
+        let parse_ms = parse_started.elapsed().as_millis() as u64;
+        // empty pages count toward the tolerance even when filters leave
+        // nothing to send
+        let page_empty = cards.is_empty();
+        let mut page_hits: Vec<PriceHit> = Vec::with_capacity(cards.len());
+        for mut hit in cards {
+            for p in &processors {
+                p.process(&mut hit).await;
+            }
+            if hit_filter.matches(&hit)
+                && session_seen.as_ref().is_none_or(|s| !s.contains(&hit.id))
+            {
+                page_hits.push(hit);
+            }
+        }
+
+        if collect {
+            collected.extend(page_hits.into_iter().filter(|h| seen.register_hit(h)));
+            total_hits = collected.len();
+            let progress = serde_json::json!({
+                "page": page,
+                "url": page_url.as_str(),
+                "status": fetched.status,
+                "collected": total_hits,
+                "total_listings": total_listings,
+                "pages_done": pages,
+                "max_pages": max_pages
+            });
+            send_event(tx, "progress", &progress.to_string()).await?;
+        } else {
+            total_hits += page_hits.len();
+            let payload = serde_json::json!({
+            "page": page,
+            "url": page_url.as_str(),
+            "status": fetched.status,
+            "final_url": fetched.final_url.as_str(),
+            "redirected": fetched.final_url != page_url,
+            "unchanged": fetched.unchanged,
+            "fetch_ms": fetch_ms,
+            "parse_ms": parse_ms,
+            "attempts": fetched.attempts,
+            "total_listings": total_listings,
+            "count": page_hits.len(),
+            "hits": page_hits,
+            "total_hits_so_far": total_hits
+            });
+            send_event(tx, "page", &payload.to_string()).await?;
+            if let Some(seen) = session_seen.as_mut() {
+                seen.extend(page_hits.iter().map(|h| h.id.clone()));
+            }
+            session_claim(params.session.as_deref(), &page_hits);
+        }
+
+        if page_empty {
+            empty_streak += 1;
+            if empty_streak >= empty_page_tolerance {
+                break;
+            }
+        } else {
+            empty_streak = 0;
+        }
+
+        prev_page_url = Some(page_url);
+        page += 1;
+
+        if pages < max_pages {
+            tokio::select! {
+                _ = sleep(Burst::pause_after(burst, pages, configured)) => {}
+                _ = until(deadline) => {}
+                _ = tx.closed() => return Err(ClientGone),
+            }
+        }
+        yield_now().await;
+    }
+
+    if timed_out {
+        let payload = serde_json::json!({
+            "total_timeout_secs": params.total_timeout_secs,
+            "pages": pages,
+            "total_hits": total_hits
+        });
+        send_event(tx, "timeout", &payload.to_string()).await?;
+    }
+
+    if collect {
+        if let Some(key) = params.sort {
+            key.sort(&mut collected);
+        }
+        let chunks = collected.len().div_ceil(COLLECT_CHUNK);
+        for (i, chunk) in collected.chunks(COLLECT_CHUNK).enumerate() {
+            let payload = serde_json::json!({ "chunk": i + 1, "chunks": chunks, "hits": chunk });
+            send_event(tx, "hits", &payload.to_string()).await?;
+            session_claim(params.session.as_deref(), chunk);
+        }
+    }
+
+    send_event(
+        tx,
+        "done",
+        &serde_json::json!({
+            "pages": pages,
+            "total_hits": total_hits,
+            "timed_out": timed_out,
+            "scraped_at": now_rfc3339()
+        })
+        .to_string(),
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape/stream",
+    params(StreamParams),
+    responses(
+        (status = 200, description = "SSE stream of `start`, `page` (or `progress` then `hits` with mode=collect), `timeout`, `done` and `error` events", content_type = "text/event-stream"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/scrape/stream")]
+async fn scrape_stream(http: HttpRequest, q: web::Query<StreamParams>) -> impl Responder {
+    let Some(stream_slot) = StreamSlot::take() else {
+        eprintln!(
+            "[stream] refusing {}: {} streams already open",
+            q.url,
+            config().max_sse_streams
+        );
+        return HttpResponse::Ok()
+            .insert_header(("Content-Type", "text/event-stream"))
+            .insert_header(("Cache-Control", "no-cache"))
+            .body(sse_error_event(
+                "too_many_streams",
+                "too many concurrent streams",
+            ));
+    };
+    let permit = match scrape_slot(&q.url, client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    let (tx, mut rx) = mpsc::channel::<StreamEvent>(32);
+    let mut params = q.into_inner();
+    params.session = params.session.or_else(|| session_header(&http));
+
+    actix_web::rt::spawn(async move {
+        // held until the producer finishes, not just until headers are sent
+        let _permit = permit;
+        let _stream_slot = stream_slot;
+        if run_stream(&tx, &params).await.is_err() {
+            eprintln!(
+                "[stream] client disconnected, scrape of {} aborted",
+                params.url
+            );
+        }
+    });
+
+    let stream = async_stream::stream! {
+        while let Some(event) = rx.recv().await {
+            yield Ok::<Bytes, actix_web::Error>(event.sse());
+        }
+    };
+
+    HttpResponse::Ok()
+        .insert_header(("Content-Type", "text/event-stream"))
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("Connection", "keep-alive"))
+        .streaming(stream)
+}
+
+// -------------------
+// CSV streaming
+// -------------------
+
+/// HTTP status for an `error` event that arrives before any CSV went out,
+/// matching what error_response gives the JSON endpoints.
+pub fn stream_error_status(code: &str) -> actix_web::http::StatusCode {
+    use actix_web::http::StatusCode;
+    match code {
+        "host_cooldown" => StatusCode::TOO_MANY_REQUESTS,
+        "circuit_open" => StatusCode::SERVICE_UNAVAILABLE,
+        "config" | "internal" => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// CSV rows for the hits of a `page` (live) or `hits` (collect) event; None
+/// for other events and for events without hits.
+pub fn csv_event_rows(event: &str, data_json: &str) -> Option<Bytes> {
+    if event != "page" && event != "hits" {
+        return None;
+    }
+    let data: serde_json::Value = serde_json::from_str(data_json).ok()?;
+    let mut rows = String::new();
+    for hit in data["hits"].as_array()? {
+        rows.push_str(&csv_row(hit));
+        rows.push('\n');
+    }
+    (!rows.is_empty()).then(|| Bytes::from(rows))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape.csv",
+    params(StreamParams),
+    responses(
+        (status = 200, description = "CSV with write_csv's columns, sent with chunked transfer encoding: the header as soon as the scrape starts, then each page's rows as it is parsed. A scrape that fails midway aborts the body, so a truncated download is detectable; resume with `url` set to the `?page=N` after the last page column received. Unlike /scrape there are no X-Claw-Pages, X-Claw-Hits or X-Claw-Duration-Ms headers: headers leave before the first row, and the server can't send HTTP trailers", content_type = "text/csv"),
+        (status = 400, description = "Invalid URL or filter, or the URL isn't allowed"),
+        (status = 429, description = "The host is cooling down after blocking us"),
+        (status = 503, description = "Too many open streams, CLAW_HOST_QUEUE_DEPTH requests already waiting for this site, or its circuit breaker is open")
+    )
+)]
+#[get("/scrape.csv")]
+async fn scrape_csv(http: HttpRequest, q: web::Query<StreamParams>) -> impl Responder {
+    let Some(stream_slot) = StreamSlot::take() else {
+        eprintln!(
+            "[csv] refusing {}: {} streams already open",
+            q.url,
+            config().max_sse_streams
+        );
+        return HttpResponse::ServiceUnavailable()
+            .json(serde_json::json!({ "error": "too many concurrent streams" }));
+    };
+    let permit = match scrape_slot(&q.url, client_id(&http)).await {
+        Ok(p) => p,
+        Err(busy) => return busy,
+    };
+    let (tx, mut rx) = mpsc::channel::<StreamEvent>(32);
+    let mut params = q.into_inner();
+    params.session = params.session.or_else(|| session_header(&http));
+    let host = Url::parse(&params.url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| "claw".to_string());
+
+    actix_web::rt::spawn(async move {
+        let _permit = permit;
+        let _stream_slot = stream_slot;
+        if run_stream(&tx, &params).await.is_err() {
+            eprintln!(
+                "[csv] client disconnected, scrape of {} aborted",
+                params.url
+            );
+        }
+    });
+
+    // validation errors come before `start`; those still get a proper status
+    match rx.recv().await {
+        Some(first) if first.event == "error" => {
+            let err: serde_json::Value = serde_json::from_str(&first.data_json).unwrap_or_default();
+            let code = err["code"].as_str().unwrap_or("internal");
+            return HttpResponse::build(stream_error_status(code))
+                .json(serde_json::json!({ "error": err["message"] }));
+        }
+        Some(_) => {}
+        None => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "scrape ended before it started" }));
+        }
+    }
+
+    let stream = async_stream::stream! {
+        yield Ok::<Bytes, actix_web::Error>(Bytes::from(format!("{}\n", PRICE_HIT_FIELDS.join(","))));
+        while let Some(event) = rx.recv().await {
+            if event.event == "error" {
+                // the 200 is long gone; cutting the body short is the only
+                // way left to tell the client the file is incomplete
+                eprintln!("[csv] scrape failed midway: {}", event.data_json);
+                yield Err(actix_web::error::ErrorBadGateway(event.data_json));
+                break;
+            }
+            if let Some(rows) = csv_event_rows(event.event, &event.data_json) {
+                yield Ok(rows);
+            }
+        }
+    };
+
+    let date = time::OffsetDateTime::now_utc().date();
+    HttpResponse::Ok()
+        .insert_header(("Content-Type", "text/csv; charset=utf-8"))
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{host}-{date}.csv\""),
+        ))
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+// -------------------
+// WebSocket streaming
+// -------------------
+
+/// What a /scrape/ws client sends first: StreamParams fields, either at the
+/// top level or under `filters`, e.g.
+/// `{"url": "...", "page_range": 5, "filters": {"max_price": 200000}}`.
+fn parse_ws_start(text: &str) -> Result<StreamParams> {
+    let mut start: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(text).context("start message must be a JSON object")?;
+    if let Some(filters) = start.remove("filters") {
+        let serde_json::Value::Object(filters) = filters else {
+            return Err(anyhow!("filters must be an object"));
+        };
+        start.extend(filters);
+    }
+    serde_json::from_value(serde_json::Value::Object(start)).context("invalid start message")
+}
+
+/// The `error` frame, then a normal close; for refusals before any scrape runs.
+async fn ws_refuse(mut session: actix_ws::Session, code: &'static str, message: String) {
+    let frame = StreamEvent::error(code, message);
+    let _ = session.text(frame.ws_frame()).await;
+    let _ = session.close(None).await;
+}
+
+/// Drives one /scrape/ws connection: waits for the start message, runs the
+/// same producer as /scrape/stream and relays its events as text frames, and
+/// stops the scrape on `{"cmd":"cancel"}` or when the socket closes.
+async fn run_ws(
+    mut session: actix_ws::Session,
+    mut msgs: actix_ws::MessageStream,
+    client: Option<String>,
+) {
+    let text = loop {
+        match msgs.recv().await {
+            Some(Ok(actix_ws::Message::Text(text))) => break text,
+            Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                if session.pong(&bytes).await.is_err() {
+                    return;
+                }
+            }
+            Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => return,
+            Some(Ok(_)) => {}
+        }
+    };
+    let params = match parse_ws_start(&text) {
+        Ok(p) => p,
+        Err(e) => return ws_refuse(session, "invalid_start", format!("{e:#}")).await,
+    };
+
+    let Some(stream_slot) = StreamSlot::take() else {
+        eprintln!(
+            "[ws] refusing {}: {} streams already open",
+            params.url,
+            config().max_sse_streams
+        );
+        let message = "too many concurrent streams".to_string();
+        return ws_refuse(session, "too_many_streams", message).await;
+    };
+    let permit = match scrape_slot(&params.url, client).await {
+        Ok(p) => p,
+        Err(busy) => {
+            // same words the HTTP endpoints answer with
+            let body = actix_web::body::to_bytes(busy.into_body())
+                .await
+                .ok()
+                .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok());
+            let message = body
+                .as_ref()
+                .and_then(|b| b["error"].as_str())
+                .unwrap_or("scrape slots busy")
+                .to_string();
+            return ws_refuse(session, "busy", message).await;
+        }
+    };
+
+    let (tx, mut rx) = mpsc::channel::<StreamEvent>(32);
+    let url = params.url.clone();
+    let producer = actix_web::rt::spawn(async move {
+        let _permit = permit;
+        let _stream_slot = stream_slot;
+        run_stream(&tx, &params).await
+    });
+
+    let mut cancelled = false;
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break };
+                if session.text(event.ws_frame()).await.is_err() {
+                    break;
+                }
+            }
+            msg = msgs.recv() => match msg {
+                Some(Ok(actix_ws::Message::Text(text))) => {
+                    let cmd = serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|v| v["cmd"].as_str().map(str::to_string));
+                    if cmd.as_deref() == Some("cancel") {
+                        cancelled = true;
+                        break;
+                    }
+                    eprintln!("[ws] ignoring message {text:?}");
+                }
+                Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    // dropping the receiver is what stops the producer: its next send, or the
+    // fetch/sleep it is waiting in, sees the channel closed
+    drop(rx);
+    let gone = matches!(producer.await, Ok(Err(ClientGone)));
+    if cancelled {
+        eprintln!("[ws] scrape of {url} cancelled by client");
+        let frame = StreamEvent {
+            event: "cancelled",
+            data_json: serde_json::json!({ "scraped_at": now_rfc3339() }).to_string(),
+        };
+        let _ = session.text(frame.ws_frame()).await;
+    } else if gone {
+        eprintln!("[ws] client disconnected, scrape of {url} aborted");
+    }
+    let _ = session.close(None).await;
+}
+
+#[utoipa::path(
+    get,
+    path = "/scrape/ws",
+    responses(
+        (status = 101, description = "WebSocket. Send a start message `{url, page_range, filters}` (StreamParams fields); receive `{\"event\", \"data\"}` text frames with the same events as /scrape/stream; send `{\"cmd\":\"cancel\"}` to stop the scrape, answered by a `cancelled` event before the close"),
+        (status = 400, description = "Not a WebSocket upgrade request")
+    )
+)]
+#[get("/scrape/ws")]
+async fn scrape_ws(http: HttpRequest, body: web::Payload) -> actix_web::Result<HttpResponse> {
+    let (response, session, msgs) = actix_ws::handle(&http, body)?;
+    let client = client_id(&http);
+    actix_web::rt::spawn(run_ws(session, msgs, client));
+    Ok(response)
+}
+
+// -------------------------
+// OpenAPI description
+// -------------------------
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Claw", description = "Njuskalo category price scraper"),
+    paths(
+        scrape_endpoint,
+        scrape_get,
+        scrape_xlsx,
+        scrape_csv,
+        scrape_pages_endpoint,
+        scrape_new,
+        scrape_profile,
+        scrape_stream,
+        scrape_ws,
+        listing_endpoint,
+        pages_endpoint,
+        scrape_estimate,
+        robots_endpoint,
+        admin_cache_clear,
+        config_endpoint
+    ),
+    components(schemas(
+        ScrapeReq,
+        ScrapePagesReq,
+        PriceHit,
+        Meta,
+        ApiResponse,
+        ListingDetail,
+        PaginationInfo,
+        ScrapeEstimate,
+        RobotsReport,
+        CacheCleared
+    ))
+)]
+struct ApiDoc;
+
+#[get("/openapi.json")]
+async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+// -------------------------
+// Tiny HTML dashboard
+// -------------------------
+
+#[get("/dashboard")]
+async fn dashboard(req: HttpRequest) -> impl Responder {
+    let bodies = dashboard_bodies();
+    let accepted = req
+        .headers()
+        .get("accept-encoding")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let (encoding, body) = if accepts_encoding(accepted, "br") {
+        (Some("br"), &bodies.br)
+    } else if accepts_encoding(accepted, "gzip") {
+        (Some("gzip"), &bodies.gzip)
+    } else {
+        (None, &bodies.plain)
+    };
+    let mut rsp = HttpResponse::Ok();
+    rsp.insert_header(("Content-Type", "text/html; charset=utf-8"))
+        .insert_header(("Vary", "Accept-Encoding"));
+    if let Some(encoding) = encoding {
+        rsp.insert_header(("Content-Encoding", encoding));
+    }
+    rsp.body(body.clone())
+}
+
+/// The dashboard with CLAW_BASE_PATH and the form defaults filled in, plain and pre-compressed;
+/// built on first request, it never changes afterwards.
+struct DashboardBodies {
+    plain: Bytes,
+    gzip: Bytes,
+    br: Bytes,
+}
+
+static DASHBOARD: OnceLock<DashboardBodies> = OnceLock::new();
+
+fn dashboard_bodies() -> &'static DashboardBodies {
+    DASHBOARD.get_or_init(|| {
+        // JSON literals, so the values land in the script as plain JS values
+        let html = DASHBOARD_HTML
+            .replace(
+                "__CLAW_BASE_PATH__",
+                &serde_json::Value::from(config().base_path.as_str()).to_string(),
+            )
+            .replace(
+                "__CLAW_DEFAULT_URL__",
+                &serde_json::Value::from(config().default_url.as_str()).to_string(),
+            )
+            .replace(
+                "__CLAW_DEFAULT_PAGE_RANGE__",
+                &config().default_page_range.to_string(),
+            );
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        let gzip = std::io::Write::write_all(&mut gzip, html.as_bytes())
+            .and_then(|_| gzip.finish())
+            .expect("gzip into a Vec");
+        let mut br = Vec::new();
+        let params = brotli::enc::BrotliEncoderParams {
+            quality: 11,
+            ..Default::default()
+        };
+        brotli::BrotliCompress(&mut html.as_bytes(), &mut br, &params).expect("brotli into a Vec");
+        eprintln!(
+            "[dashboard] {} bytes, gzip {}, br {}",
+            html.len(),
+            gzip.len(),
+            br.len()
+        );
+        DashboardBodies {
+            plain: Bytes::from(html),
+            gzip: Bytes::from(gzip),
+            br: Bytes::from(br),
+        }
+    })
+}
+
+/// `coding` is acceptable per Accept-Encoding: its own entry decides when
+/// present, so `br;q=0` refuses br even alongside `*`; otherwise `*` does.
+pub fn accepts_encoding(header: &str, coding: &str) -> bool {
+    let mut wildcard = None;
+    for item in header.split(',') {
+        let mut parts = item.split(';').map(str::trim);
+        let name = parts.next().unwrap_or("");
+        // the weight's name is case-insensitive and may have spaces around `=`
+        let refused = parts.any(|p| {
+            p.split_once('=')
+                .filter(|(k, _)| k.trim().eq_ignore_ascii_case("q"))
+                .and_then(|(_, q)| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        if name.eq_ignore_ascii_case(coding) {
+            return !refused;
+        }
+        if name == "*" {
+            wildcard = Some(!refused);
+        }
+    }
+    wildcard.unwrap_or(false)
+}
+
+const DASHBOARD_HTML: &str = r#"
+<!doctype html>
+<html lang="en" class="dark">
+<head>
+  <meta charset="utf-8" />
+  <title>Claw Dashboard</title>
+
+  <!-- Tailwind (CDN) -->
+  <script>
+    tailwind.config = { darkMode: 'class' };
+  </script>
+  <script src="https://cdn.tailwindcss.com"></script>
+
+  <!-- Alpine.js (CDN) -->
+  <script defer src="https://unpkg.com/alpinejs@3.x.x/dist/cdn.min.js"></script>
+
+  <meta name="viewport" content="width=device-width, initial-scale=1" />
+  <style>[x-cloak]{display:none!important}</style>
+</head>
+<body class="bg-slate-900 text-slate-100 antialiased">
+  <!-- App fills the viewport height -->
+  <main class="max-w-6xl mx-auto p-6 flex flex-col gap-6 h-dvh"
+        x-data="flatwatch()"
+        x-init="init()">
+
+    <div class="flex items-center justify-between">
+      <h1 class="text-3xl font-bold tracking-tight shrink-0">Claw Dashboard</h1>
+      <!-- (no theme toggle anymore) -->
+    </div>
+
+    <!-- Controls -->
+    <div class="bg-slate-800 shadow-sm ring-1 ring-slate-700 rounded-xl p-4 space-y-4 shrink-0">
+      <div class="grid grid-cols-1 md:grid-cols-4 gap-3 items-center">
+        <label class="md:col-span-1 text-sm font-medium text-slate-300">Category URL</label>
+        <input x-model="url"
+               type="text"
+               class="md:col-span-3 w-full rounded-lg border-slate-700 bg-slate-900 text-slate-100 focus:border-indigo-500 focus:ring-indigo-500 px-2 py-1.5 text-sm"
+               :placeholder="defaultUrl">
+
+        <label class="md:col-span-1 text-sm font-medium text-slate-300">page_range</label>
+        <input x-model.number="pageRange"
+               type="number" min="1" max="500"
+               class="md:col-span-1 w-full rounded-lg border-slate-700 bg-slate-900 text-slate-100 focus:border-indigo-500 focus:ring-indigo-500 px-2 py-1.5 text-sm"
+               :placeholder="defaultPageRange">
+        
+        <div class="md:col-span-2 flex items-center gap-3">
+        <button @click="start()"
+                :disabled="isRunning"
+                class="inline-flex items-center gap-2 px-2 py-1 text-sm rounded-md bg-indigo-600 text-white font-medium hover:bg-indigo-700 disabled:opacity-50 disabled:cursor-not-allowed">
+            <svg x-show="!isRunning" xmlns="http://www.w3.org/2000/svg" class="h-3.5 w-3.5" fill="none" viewBox="0 0 24 24" stroke="currentColor"><path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M5 12h14M12 5l7 7-7 7"/></svg>
+            <svg x-show="isRunning" xmlns="http://www.w3.org/2000/svg" class="animate-spin h-3.5 w-3.5" viewBox="0 0 24 24" fill="none"><circle class="opacity-30" cx="12" cy="12" r="10" stroke="currentColor" stroke-width="4"/><path class="opacity-80" fill="currentColor" d="M4 12a8 8 0 018-8v4a4 4 0 00-4 4H4z"/></svg>
+            <span class="text-sm" x-text="isRunning ? 'Running…' : 'Start'"></span>
+        </button>
+
+        <!-- CSV export button -->
+        <button @click="downloadCSV()"
+                :disabled="rows.length === 0"
+                class="inline-flex items-center gap-2 px-2 py-1 text-sm rounded-md bg-slate-700 text-slate-100 font-medium hover:bg-slate-600 disabled:opacity-50 disabled:cursor-not-allowed">
+            <svg xmlns="http://www.w3.org/2000/svg" class="h-3.5 w-3.5" viewBox="0 0 24 24" fill="currentColor"><path d="M12 3a1 1 0 011 1v9.586l2.293-2.293a1 1 0 111.414 1.414l-4.007 4.007a1 1 0 01-1.414 0L7.279 12.707a1 1 0 111.414-1.414L11 13.586V4a1 1 0 011-1z"/><path d="M5 15a1 1 0 112 0v3h10v-3a1 1 0 112 0v3a2 2 0 01-2 2H7a2 2 0 01-2-2v-3z"/></svg>
+            <span class="text-sm">Export CSV</span>
+        </button>
+        </div>
+      </div>
+      
+    </div>
+
+    <!-- Log (collapsed by default) -->
+    <div class="bg-slate-800 shadow-sm ring-1 ring-slate-700 rounded-xl p-4 shrink-0">
+      <div class="flex items-center justify-between">
+        <div class="text-sm font-semibold text-slate-300">Log</div>
+        <div class="text-sm text-slate-300 flex gap-4">
+        <div><span class="font-semibold">Pages:</span> <span x-text="stats.pages"></span></div>
+        <div><span class="font-semibold">Total hits:</span> <span x-text="stats.totalHits"></span><span x-show="stats.totalListings != null" x-text="` of ${stats.totalListings}`"></span></div>
+        <div><span class="font-semibold">Last:</span> <span x-text="lastPageMsg || '-'"></span></div>
+      </div>
+        <button
+          @click="logOpen = !logOpen"
+          class="text-xs px-2 py-1 rounded-md bg-slate-700 text-slate-100 hover:bg-slate-600">
+          <span x-text="logOpen ? 'Hide' : 'Show'"></span>
+        </button>
+      </div>
+      <div x-show="logOpen" x-cloak class="mt-2">
+        <pre id="log"
+             class="h-36 overflow-auto whitespace-pre-wrap text-sm leading-relaxed text-slate-200 bg-slate-900/40 rounded-md p-2"
+             x-text="logs.join('\n')"></pre>
+      </div>
+    </div>
+
+    <!-- Results -->
+    <div class="bg-slate-800 shadow-sm ring-1 ring-slate-700 rounded-xl p-4 flex-1 min-h-0 flex flex-col">
+      <div class="flex-1 min-h-0 overflow-y-auto rounded-lg">
+        <table class="min-w-full text-sm">
+          <thead class="bg-slate-700 sticky top-0 z-10">
+            <tr class="text-left text-slate-100">
+              <th class="px-3 py-2 font-medium">#</th>
+              <th class="px-3 py-2 font-medium">Page</th>
+              <th class="px-3 py-2 font-medium">Title</th>
+              <th class="px-3 py-2 font-medium">Price</th>
+              <th class="px-3 py-2 font-medium">Currency</th>
+              <th class="px-3 py-2 font-medium">m²</th>
+              <th class="px-3 py-2 font-medium">€/m²</th>
+              <th class="px-3 py-2 font-medium">Photos</th>
+              <th class="px-3 py-2 font-medium">URL</th>
+            </tr>
+          </thead>
+          <tbody>
+            <template x-for="row in rows" :key="row._k">
+              <tr class="border-t border-slate-700 hover:bg-slate-700/50">
+                <td class="px-3 py-2" x-text="row.idx"></td>
+                <td class="px-3 py-2" x-text="row.page"></td>
+                <td class="px-3 py-2"><span class="line-clamp-2" x-text="row.title"></span></td>
+                <td class="px-3 py-2 tabular-nums" x-text="row.price_numeric ?? ''"></td>
+                <td class="px-3 py-2" x-text="row.currency ?? ''"></td>
+                <td class="px-3 py-2 tabular-nums" x-text="row.sqm ?? ''"></td>
+                <td class="px-3 py-2 tabular-nums" x-text="row.price_per_m2_round ?? ''"></td>
+                <td class="px-3 py-2 tabular-nums" x-text="row.image_count ?? ''"></td>
+                <td class="px-3 py-2">
+                  <a class="text-indigo-400 hover:underline" :href="row.listing_url" target="_blank">open</a>
+                </td>
+              </tr>
+            </template>
+          </tbody>
+        </table>
+      </div>
+    </div>
+  </main>
+
+  <script>
+    // CLAW_BASE_PATH, filled in by the server ("" when served at the root)
+    const BASE_PATH = __CLAW_BASE_PATH__;
+    // CLAW_DEFAULT_URL and CLAW_DEFAULT_PAGE_RANGE, filled in the same way
+    const DEFAULT_URL = __CLAW_DEFAULT_URL__;
+    const DEFAULT_PAGE_RANGE = __CLAW_DEFAULT_PAGE_RANGE__;
+
+    function flatwatch() {
+      return {
+        // form state
+        url: DEFAULT_URL,
+        pageRange: DEFAULT_PAGE_RANGE,
+        defaultUrl: DEFAULT_URL,
+        defaultPageRange: DEFAULT_PAGE_RANGE,
+
+        // runtime state
+        isRunning: false,
+        rows: [],
+        logs: [],
+        stats: { pages: 0, totalHits: 0, totalListings: null },
+        lastPageMsg: '',
+        logOpen: false, // collapsed by default
+
+        _es: null,
+        _idx: 0,
+
+        init() {},
+        log(msg) {
+          this.logs.push(msg);
+          this.$nextTick(() => {
+            const el = document.getElementById('log');
+            if (el) el.scrollTop = el.scrollHeight;
+          });
+        },
+
+        start() {
+          if (!this.url) { this.log('Please enter a category URL.'); return; }
+          if (this._es) { try { this._es.close(); } catch (_) {} this._es = null; }
+          this.rows = [];
+          this.logs = [];
+          this.stats = { pages: 0, totalHits: 0, totalListings: null };
+          this.lastPageMsg = '-';
+          this._idx = 0;
+
+          const qs = new URLSearchParams({ url: this.url, page_range: String(this.pageRange || DEFAULT_PAGE_RANGE) });
+          const sseUrl = `${BASE_PATH}/scrape/stream?${qs.toString()}`;
+          this.log(`Connecting: ${sseUrl}`);
+          this.isRunning = true;
+
+          const es = new EventSource(sseUrl);
+          this._es = es;
+
+          es.addEventListener('start', (ev) => this.log(`START: ${ev.data}`));
+
+          es.addEventListener('page', (ev) => {
+            const data = JSON.parse(ev.data || '{}');
+            const pageNo = data.page ?? '?';
+            const hits = Array.isArray(data.hits) ? data.hits : [];
+            this.stats.pages += 1;
+            this.stats.totalHits += hits.length;
+            if (data.total_listings != null) this.stats.totalListings = data.total_listings;
+            this.lastPageMsg = `PAGE ${pageNo} (${hits.length} items)`;
+            this.log(`${this.lastPageMsg} fetch=${data.fetch_ms ?? '?'}ms parse=${data.parse_ms ?? '?'}ms attempts=${data.attempts ?? '?'}`);
+            if ((data.attempts ?? 1) > 1) {
+              this.log(`  needed ${data.attempts} attempts, possible partial blocking`);
+            }
+            if (data.redirected) {
+              this.log(`  redirected (${data.status}): ${data.url} → ${data.final_url}`);
+            }
+
+            hits.forEach(h => {
+              this.rows.push({
+                _k: `${pageNo}-${h.id || Math.random()}`,
+                idx: ++this._idx,
+                page: pageNo,
+                title: (h.title || '').replace(/</g, '&lt;'),
+                price_numeric: h.price_numeric,
+                currency: h.currency,
+                sqm: h.sqm,
+                price_per_m2_round: h.price_per_m2_rounded ?? null,
+                image_count: h.image_count ?? null,
+                listing_url: h.listing_url
+              });
+            });
+          });
+
+          es.addEventListener('done', (ev) => {
+            this.log(`DONE: ${ev.data}`);
+            this.isRunning = false;
+            es.close();
+            this._es = null;
+          });
+
+          es.addEventListener('error', (ev) => {
+            let err = null;
+            try { err = ev && ev.data ? JSON.parse(ev.data) : null; } catch (_) {}
+            if (!err) {
+              this.log('ERROR: (connection error) — closing stream');
+            } else if (err.code === 'domain_not_allowed' || err.code === 'path_not_allowed' || err.code === 'invalid_url') {
+              this.log(`ERROR: ${err.message} — check the category URL`);
+            } else if (err.code === 'robots_disallowed') {
+              this.log(`ERROR: ${err.message} — this path can't be scraped`);
+            } else {
+              this.log(`ERROR [${err.code}]: ${err.message} — closing stream`);
+            }
+            this.isRunning = false;
+            es.close();
+            this._es = null;
+          });
+        },
+
+        // CSV export
+        downloadCSV() {
+          if (!this.rows.length) return;
+
+          const headers = ['idx','page','title','price_numeric','currency','sqm','price_per_m2_round','image_count','listing_url'];
+          const esc = (v) => {
+            if (v === null || v === undefined) return '';
+            const s = String(v);
+            return /[",\n]/.test(s) ? `"${s.replace(/"/g, '""')}"` : s;
+          };
+
+          const lines = [
+            headers.join(','),
+            ...this.rows.map(r => headers.map(h => esc(r[h])).join(','))
+          ];
+
+          const blob = new Blob([lines.join('\n')], { type: 'text/csv;charset=utf-8;' });
+          const url = URL.createObjectURL(blob);
+          const a = document.createElement('a');
+          a.href = url;
+          a.download = `flatwatch_${new Date().toISOString().slice(0,19).replace(/[:T]/g,'-')}.csv`;
+          document.body.appendChild(a);
+          a.click();
+          setTimeout(() => {
+            document.body.removeChild(a);
+            URL.revokeObjectURL(url);
+          }, 0);
+        },
+      }
+    }
+  </script>
+</body>
+</html>
+"#;
+
+/// Where serve() listens.
+const BIND_ADDR: (&str, u16) = ("0.0.0.0", 8080);
+
+/// Binds 0.0.0.0:8080 and serves until shutdown; all the `claw` binary does.
+pub async fn serve() -> std::io::Result<()> {
+    // load env config up front so its warnings show at startup
+    let _ = config();
+    let uas = ua_pools();
+    eprintln!(
+        "[ua] pools: desktop={} mobile={}",
+        uas.desktop.len(),
+        uas.mobile.len()
+    );
+    let cfg = config();
+    eprintln!(
+        "[pool] max_idle_per_host={} idle_timeout={:?} tcp_keepalive={:?}",
+        cfg.pool_max_idle_per_host, cfg.pool_idle_timeout, cfg.tcp_keepalive
+    );
+    eprintln!(
+        "Starting Claw on {}:{} … (max concurrent scrapes: {})",
+        BIND_ADDR.0,
+        BIND_ADDR.1,
+        config().max_concurrent_scrapes
+    );
+    let server = HttpServer::new(|| {
+        // everything lives under CLAW_BASE_PATH; "" is the root
+        App::new().service(
+            web::scope(&config().base_path)
+                .service(index)
+                .service(healthz)
+                .service(scrape_endpoint)
+                .service(scrape_get) // GET JSON
+                .service(scrape_xlsx) // GET Excel export
+                .service(scrape_csv) // GET streamed CSV
+                .service(scrape_pages_endpoint) // explicit page list
+                .service(scrape_new) // diff against stored history
+                .service(scrape_profile) // saved search profiles
+                .service(listing_endpoint) // single listing detail
+                .service(pages_endpoint) // pagination info only
+                .service(scrape_estimate) // request/time projection
+                .service(robots_endpoint) // robots.txt decision
+                .service(admin_cache_clear) // flush in-process caches
+                .service(config_endpoint) // effective runtime config
+                .service(scrape_stream) // SSE stream
+                .service(scrape_ws) // WebSocket stream
+                .service(dashboard) // Minimal UI
+                .service(openapi_json), // OpenAPI 3 spec
+        )
+    });
+    let server = match config().workers {
+        Some(n) => server.workers(n),
+        None => server,
+    };
+    server.bind(BIND_ADDR)?.run().await
+}
+
+// -------------------------
+// Hit processors (post-processing plugins)
+// -------------------------
+
+/// Rewrites or enriches each parsed hit (geocoding, tagging, extra prices) on
+/// every scrape path: /scrape and its exports, /scrape/pages, the streams and
+/// the CLI. Runs after parsing (and `convert_to` where the request has one)
+/// but before filters apply, so it also sees hits a filter then drops.
+///
+/// Register custom processors before starting the server; with
+/// CLAW_HIT_PROCESSORS unset every registered one runs, in registration order:
+///
+/// ```no_run
+/// struct Neighborhood;
+///
+/// #[claw::async_trait]
+/// impl claw::HitProcessor for Neighborhood {
+///     fn name(&self) -> &str {
+///         "neighborhood"
+///     }
+///
+///     async fn process(&self, hit: &mut claw::PriceHit) {
+///         if hit.title.contains("Trešnjevka") {
+///             hit.flags.push("tresnjevka".to_string());
+///         }
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> std::io::Result<()> {
+///     claw::register_hit_processor(Neighborhood);
+///     claw::serve().await
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait HitProcessor: Send + Sync {
+    /// What CLAW_HIT_PROCESSORS calls it.
+    fn name(&self) -> &str;
+    async fn process(&self, hit: &mut PriceHit);
+}
+
+pub use async_trait::async_trait;
+
+/// Built-in example: converts EUR/HRK prices like `convert_to` does.
+/// Available as `convert_to_eur` and `convert_to_hrk`.
+struct ConvertCurrency(Currency);
+
+#[async_trait::async_trait]
+impl HitProcessor for ConvertCurrency {
+    fn name(&self) -> &str {
+        match self.0 {
+            Currency::Eur => "convert_to_eur",
+            Currency::Hrk => "convert_to_hrk",
+        }
+    }
+
+    async fn process(&self, hit: &mut PriceHit) {
+        convert_hit(hit, self.0);
+    }
+}
+
+static HIT_PROCESSORS: Mutex<Vec<Arc<dyn HitProcessor>>> = Mutex::new(Vec::new());
+
+/// Adds `processor` to the registry scrapes pick their chain from.
+pub fn register_hit_processor(processor: impl HitProcessor + 'static) {
+    HIT_PROCESSORS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Arc::new(processor));
+}
+
+/// The chain a scrape runs: CLAW_HIT_PROCESSORS names in order, looked up
+/// among registered and built-in processors; unset, every registered one.
+/// Unknown names become warnings.
+fn hit_processor_chain(warnings: &mut Vec<String>) -> Vec<Arc<dyn HitProcessor>> {
+    let registered = HIT_PROCESSORS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let Some(names) = &config().hit_processors else {
+        return registered;
+    };
+    let builtin: [Arc<dyn HitProcessor>; 2] = [
+        Arc::new(ConvertCurrency(Currency::Eur)),
+        Arc::new(ConvertCurrency(Currency::Hrk)),
+    ];
+    names
+        .iter()
+        .filter_map(|name| {
+            let found = registered
+                .iter()
+                .chain(&builtin)
+                .find(|p| p.name() == name)
+                .cloned();
+            if found.is_none() {
+                warnings.push(format!("unknown hit processor {name:?} skipped"));
+            }
+            found
+        })
+        .collect()
+}
+
+// -------------------------
+// Core scraper (auto-paging; one client + cookie jar per job)
+// -------------------------
+
+const HARD_PAGE_CAP: usize = 200; // sanity guard
+/// Politeness delay between category pages, unless CLAW_MIN/MAX_DELAY_MS say otherwise.
+const PAGE_DELAY_MS: std::ops::Range<u64> = 900..2200;
+
+/// Bounds of the random pause between pages, inclusive.
+#[derive(Serialize, ToSchema, Clone, Copy, Debug)]
+struct DelayRange {
+    min_ms: u64,
+    max_ms: u64,
+}
+
+impl DelayRange {
+    /// The server's CLAW_MIN_DELAY_MS..=CLAW_MAX_DELAY_MS.
+    fn configured() -> Self {
+        let c = config();
+        DelayRange {
+            min_ms: c.min_delay_ms,
+            max_ms: c.max_delay_ms,
+        }
+    }
+
+    /// A scrape's own bounds on top of the configured ones. Both are kept
+    /// within CLAW_DELAY_FLOOR_MS..=CLAW_DELAY_CEILING_MS, and a min above the
+    /// max drags the max up with it.
+    fn for_request(min: Option<u64>, max: Option<u64>, warnings: &mut Vec<String>) -> Self {
+        let defaults = Self::configured();
+        let (floor, ceiling) = (config().delay_floor_ms, config().delay_ceiling_ms);
+        let mut clamp = |name: &str, v: u64| {
+            if v < floor {
+                warnings.push(format!("{name} {v} raised to the server floor of {floor}"));
+                floor
+            } else if v > ceiling {
+                warnings.push(format!(
+                    "{name} {v} lowered to the server ceiling of {ceiling}"
+                ));
+                ceiling
+            } else {
+                v
+            }
+        };
+        let min_ms = min.map_or(defaults.min_ms, |v| clamp("min_delay_ms", v));
+        let max_ms = max.map_or(defaults.max_ms, |v| clamp("max_delay_ms", v));
+        DelayRange {
+            min_ms,
+            max_ms: max_ms.max(min_ms),
+        }
+    }
+
+    fn sample(self) -> Duration {
+        Duration::from_millis(rng().random_range(self.min_ms..=self.max_ms))
+    }
+
+    fn midpoint(self) -> u64 {
+        self.min_ms.midpoint(self.max_ms)
+    }
+}
+
+/// "Burst then rest" pacing: `size` pages at the usual delay, then one rest.
+#[derive(Serialize, ToSchema, Clone, Copy, Debug)]
+struct Burst {
+    size: usize,
+    rest_ms: u64,
+}
+
+/// Longest rest between bursts a request or CLAW_BURST_REST_MS may set.
+const MAX_BURST_REST_MS: u64 = 10 * 60 * 1000;
+
+impl Burst {
+    /// None when pacing is flat (burst size 0). A rest shorter than the
+    /// longest normal pause would be no rest at all, so it's raised to that;
+    /// one above MAX_BURST_REST_MS is lowered with a warning.
+    fn for_request(
+        size: Option<usize>,
+        rest_ms: Option<u64>,
+        delay: DelayRange,
+        warnings: &mut Vec<String>,
+    ) -> Option<Self> {
+        let size = size.unwrap_or(config().burst_size);
+        if size == 0 {
+            return None;
+        }
+        let rest_ms = match rest_ms {
+            Some(v) if v > MAX_BURST_REST_MS => {
+                warnings.push(format!(
+                    "burst_rest_ms {v} lowered to the server ceiling of {MAX_BURST_REST_MS}"
+                ));
+                MAX_BURST_REST_MS
+            }
+            Some(v) => v,
+            None => config().burst_rest_ms,
+        };
+        Some(Burst {
+            size,
+            rest_ms: rest_ms.max(delay.max_ms),
+        })
+    }
+
+    /// The pause after the `pages`th page: a jittered rest at the end of each
+    /// burst, the usual delay otherwise.
+    fn pause_after(burst: Option<Self>, pages: usize, delay: DelayRange) -> Duration {
+        match burst {
+            Some(b) if pages.is_multiple_of(b.size) => {
+                let jitter = b.rest_ms / 5;
+                let ms = rng().random_range(
+                    b.rest_ms.saturating_sub(jitter)..=b.rest_ms.saturating_add(jitter),
+                );
+                eprintln!("[pager] burst of {} pages done, resting {}ms", b.size, ms);
+                Duration::from_millis(ms)
+            }
+            _ => delay.sample(),
+        }
+    }
+}
+
+pub async fn scrape_prices(req: &ScrapeReq) -> Result<(Vec<PriceHit>, Meta)> {
+    let url = Url::parse(&req.url).context("invalid url")?;
+    let hit_filter = HitFilter::new(req)?;
+    let accept_language = req
+        .accept_language
+        .as_deref()
+        .map(parse_accept_language)
+        .transpose()?;
+    let fetch_opts = FetchOpts {
+        accept_language: accept_language.as_ref(),
+        token: None,
+    };
+    let (host, adapter) = ensure_allowed(&url).await?;
+    let category_check = match req.success_marker.as_deref().map(str::trim) {
+        Some(marker) if !marker.is_empty() => {
+            eprintln!("[fetch] success_marker override: {marker:?}");
+            adapter.category_page.with_marker(marker)
+        }
+        _ => adapter.category_page,
+    };
+
+    let (base, mut page) = normalize_pager(&url);
+    let selectors = CardSelectors::new()?;
+
+    let mut hits: Vec<PriceHit> = Vec::new();
+    let mut seen = SeenHits::new(req.dedup_mode);
+    let mut pages = 0usize;
+    let mut last_next_url: Option<String> = None;
+    let origin = format!("{}://{}", base.scheme(), host);
+    let mut prev_page_url: Option<Url> = None;
+    // follow_next: the site's own next link, fetched verbatim
+    let mut followed_next: Option<Url> = None;
+
+    let max_pages = if req.quick {
+        1
+    } else {
+        req.page_range.unwrap_or(HARD_PAGE_CAP)
+    };
+    let empty_page_tolerance = req.empty_page_tolerance.unwrap_or(1).max(1);
+    let mut empty_pages = 0usize;
+    let mut empty_streak = 0usize;
+    let mut unchanged_pages: Vec<usize> = Vec::new();
+    let mut fetched_pages: Vec<(usize, String)> = Vec::new();
+    let mut no_results = false;
+    let mut currency_breakdown: BTreeMap<String, usize> = BTreeMap::new();
+    let mut dropped_session_seen = 0usize;
+    let session_seen = req.session.as_deref().map(session_seen);
+    let mut dropped_unpriced = 0usize;
+    let deadline = scrape_deadline(req.total_timeout_secs);
+    let mut timed_out = false;
+    let mut warnings: Vec<String> = Vec::new();
+    let mut total_listings: Option<usize> = None;
+    let delay = DelayRange::for_request(req.min_delay_ms, req.max_delay_ms, &mut warnings);
+    let burst = Burst::for_request(req.burst_size, req.burst_rest_ms, delay, &mut warnings);
+    let processors = hit_processor_chain(&mut warnings);
+    let stop_after_hits = req.stop_after_hits.filter(|n| *n > 0);
+    let mut target_reached = false;
+    let mut since_cutoff = false;
+
+    // one client per job: the warmup's session cookies carry into every page;
+    // quick previews skip it, one page rarely needs them
+    let client = build_client()?;
+    let mut token = None;
+    if !req.quick {
+        // a deadline hit here is caught by the first fetch below
+        tokio::select! {
+            t = warmup_hit(&client, &origin, fetch_opts, adapter) => token = t,
+            _ = until(deadline) => {}
+        }
+    }
+    let fetch_opts = fetch_opts.with_token(token.as_ref());
+
+    loop {
+        if pages >= max_pages {
+            eprintln!("[pager] reached max_pages={}, stopping.", max_pages);
+            break;
+        }
+
+        let page_url = match followed_next.take() {
+            Some(u) => u,
+            None => build_page_url(&base, page).context("build page url failed")?,
+        };
+
+        let referer = req
+            .referer_strategy
+            .referer(prev_page_url.as_ref(), &origin);
+
+        let fetched = tokio::select! {
+            r = retry_fetch_html(&client, &page_url, &referer, &category_check, fetch_opts) => r,
+            _ = until(deadline) => {
+                eprintln!("[pager] total_timeout_secs reached before {}, stopping.", page_url);
+                timed_out = true;
+                break;
+            }
+        };
+        let fetched = match fetched {
+            Ok(f) => f,
+            // a blocked or rejected page counts toward the tolerance like an
+            // empty one; the one that exhausts it still fails the scrape
+            Err(e) if counts_as_empty_page(&e) && empty_streak + 1 < empty_page_tolerance => {
+                pages += 1;
+                empty_pages += 1;
+                empty_streak += 1;
+                eprintln!(
+                    "[pager] page {} failed, empty page {}/{}, carrying on: {e:#}",
+                    page, empty_streak, empty_page_tolerance
+                );
+                warnings.push(format!("page {page} skipped: {e:#}"));
+                last_next_url = Some(build_page_url(&base, page + 1)?.to_string());
+                prev_page_url = Some(page_url);
+                page += 1;
+                if pages < max_pages {
+                    tokio::select! {
+                        _ = sleep(Burst::pause_after(burst, pages, delay)) => {}
+                        _ = until(deadline) => {}
+                    }
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        pages += 1;
+        fetched_pages.push((page, page_url.to_string()));
+        if let Some(w) = page_param_dropped(&page_url, &fetched.final_url) {
+            warnings.push(w);
+        }
+        if fetched.unchanged {
+            unchanged_pages.push(page);
+        }
+        let html = fetched.html;
+
+        let probe = html.replace('\n', " ");
+        eprintln!(
+            "[{}] len={} has(EntityList)={} has(EntityList-item)={} url={} referer={}",
+            page,
+            probe.len(),
+            probe.contains("EntityList"),
+            probe.contains("EntityList-item"),
+            page_url,
+            referer
+        );
+
+        let doc = Html::parse_document(&html);
+        if pages == 1 {
+            total_listings = extract_total_listings(&doc);
+        }
+
+        // parse cards; `empty_page_tolerance` pages in a row with nothing new
+        // (e.g. the site repeating its last page) end the scrape, filtered-out
+        // cards still count as new
+        let mut page_count = 0usize;
+        let parse_opts = ParseOpts {
+            include_text: req.include_text,
+            max_per_page: req.max_per_page.filter(|n| *n > 0),
+        };
+        let cards = parse_page_cards(&doc, &page_url, page, &selectors, parse_opts);
+        // an empty search says so on its first page; no point paging on. Only
+        // when nothing parsed: a promo block may reuse the marker class.
+        if pages == 1 && cards.is_empty() && adapter.category_page.is_empty_result(&doc) {
+            eprintln!("[pager] no results for {}, stopping.", page_url);
+            no_results = true;
+            last_next_url = None;
+            break;
+        }
+        // newest-first lists: once a whole page predates `since`, so will the rest
+        if hit_filter.page_predates_since(&cards) {
+            since_cutoff = true;
+        }
+        for mut hit in cards {
+            if !seen.register_hit(&hit) {
+                continue;
+            }
+            page_count += 1;
+            // after dedup so unpriced cards still count as new for the
+            // empty-page check, before anything spends time on them
+            if req.only_priced && hit.price_numeric.is_none() {
+                dropped_unpriced += 1;
+                continue;
+            }
+            let key = currency_key(&hit);
+            if let Some(to) = req.convert_to {
+                convert_hit(&mut hit, to);
+            }
+            for p in &processors {
+                p.process(&mut hit).await;
+            }
+            if hit_filter.matches(&hit) {
+                if session_seen.as_ref().is_some_and(|s| s.contains(&hit.id)) {
+                    dropped_session_seen += 1;
+                    continue;
+                }
+                *currency_breakdown.entry(key).or_default() += 1;
+                hits.push(hit);
+                if stop_after_hits.is_some_and(|n| hits.len() >= n) {
+                    target_reached = true;
+                    break;
+                }
+            }
+        }
+
+        eprintln!(
+            "[{}] page={} cards={} total_hits={}",
+            page,
+            page_url,
+            page_count,
+            hits.len()
+        );
+
+        if page_count == 0 {
+            empty_pages += 1;
+            empty_streak += 1;
+            if empty_streak >= empty_page_tolerance {
+                last_next_url = None;
+                break;
+            }
+            eprintln!(
+                "[pager] empty page {}/{}, carrying on",
+                empty_streak, empty_page_tolerance
+            );
+        } else {
+            empty_streak = 0;
+        }
+        if req.follow_next {
+            followed_next = find_next_page_url(&doc, &page_url).filter(|next| {
+                whitelisted(next).is_ok() && !fetched_pages.iter().any(|(_, u)| u == next.as_str())
+            });
+            if followed_next.is_none() {
+                eprintln!("[pager] no usable next link on {}, synthesizing", page_url);
+            }
+        }
+        last_next_url = Some(match &followed_next {
+            Some(u) => u.to_string(),
+            None => build_page_url(&base, page + 1)?.to_string(),
+        });
+        if target_reached {
+            eprintln!(
+                "[pager] stop_after_hits={} reached on page {}, stopping.",
+                hits.len(),
+                page
+            );
+            break;
+        }
+        if since_cutoff {
+            eprintln!(
+                "[pager] every listing on page {} predates since, stopping.",
+                page
+            );
+            break;
+        }
+        prev_page_url = Some(page_url);
+        page += 1;
+        // no politeness delay after the last page we're going to fetch
+        if pages < max_pages {
+            tokio::select! {
+                _ = sleep(Burst::pause_after(burst, pages, delay)) => {}
+                _ = until(deadline) => {}
+            }
+        }
+        yield_now().await;
+    }
+
+    let meta = Meta {
+        scraped_at: Some(now_rfc3339()),
+        page_count: pages,
+        total_hits: hits.len(),
+        total_listings,
+        next_url: last_next_url,
+        title_include: req.title_include.clone(),
+        title_exclude: req.title_exclude.clone(),
+        new_build: req.new_build,
+        dedup_mode: req.dedup_mode,
+        empty_page_tolerance,
+        empty_pages,
+        no_results,
+        currency_breakdown,
+        timed_out,
+        partial: timed_out,
+        unchanged_pages,
+        fetched_pages,
+        dropped_duplicate_id: seen.dropped_id,
+        dropped_duplicate_content: seen.dropped_content,
+        dropped_session_seen,
+        dropped_unpriced,
+        delay_ms: Some(delay),
+        burst,
+        max_per_page: req.max_per_page.filter(|n| *n > 0),
+        stop_after_hits,
+        target_reached,
+        since: hit_filter.since.map(|d| d.to_string()),
+        since_cutoff,
+        warnings,
+        ..Default::default()
+    };
+    Ok((hits, meta))
+}
+
+/// Per-hit filters from the request; title regexes are compiled once per scrape.
+pub struct HitFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+    new_build: Option<bool>,
+    exclude_status: Vec<&'static str>,
+    since: Option<time::Date>,
+    strict_since: bool,
+    price: (Option<f64>, Option<f64>),
+    sqm: (Option<f64>, Option<f64>),
+}
+
+impl HitFilter {
+    pub fn new(req: &ScrapeReq) -> Result<Self> {
+        Ok(Self {
+            new_build: req.new_build,
+            exclude_status: parse_statuses(req.exclude_status.as_deref())?,
+            since: req
+                .since
+                .as_deref()
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| {
+                    parse_posted_date(s)
+                        .ok_or_else(|| anyhow!("invalid since {s:?}, expected YYYY-MM-DD"))
+                })
+                .transpose()?,
+            strict_since: req.strict_since,
+            price: (req.min_price, req.max_price),
+            sqm: (req.min_sqm, req.max_sqm),
+            ..Self::titles(req.title_include.as_deref(), req.title_exclude.as_deref())?
+        })
+    }
+
+    fn for_stream(params: &StreamParams) -> Result<Self> {
+        Ok(Self {
+            new_build: params.new_build,
+            exclude_status: parse_statuses(params.exclude_status.as_deref())?,
+            price: (params.min_price, params.max_price),
+            sqm: (params.min_sqm, params.max_sqm),
+            ..Self::titles(
+                params.title_include.as_deref(),
+                params.title_exclude.as_deref(),
+            )?
+        })
+    }
+
+    /// Only the title regexes set; everything else lets hits through.
+    fn titles(include: Option<&str>, exclude: Option<&str>) -> Result<Self> {
+        let compile = |field: &str, pattern: Option<&str>| -> Result<Option<Regex>> {
+            pattern
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    RegexBuilder::new(p)
+                        .case_insensitive(true)
+                        .build()
+                        .with_context(|| format!("invalid {field} regex"))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            include: compile("title_include", include)?,
+            exclude: compile("title_exclude", exclude)?,
+            new_build: None,
+            exclude_status: Vec::new(),
+            since: None,
+            strict_since: false,
+            price: (None, None),
+            sqm: (None, None),
+        })
+    }
+
+    pub fn matches(&self, hit: &PriceHit) -> bool {
+        let title = hit.title.as_str();
+        self.include.as_ref().is_none_or(|re| re.is_match(title))
+            && !self.exclude.as_ref().is_some_and(|re| re.is_match(title))
+            && self.new_build.is_none_or(|want| hit.new_build == want)
+            && !hit
+                .status
+                .as_deref()
+                .is_some_and(|s| self.exclude_status.contains(&s))
+            && self.since.is_none_or(|since| match posted_on(hit) {
+                Some(d) => d >= since,
+                None => !self.strict_since,
+            })
+            && in_range(hit.price_numeric, self.price)
+            && in_range(hit.sqm, self.sqm)
+    }
+
+    /// With `since`, a page whose every card is dated before it. An empty
+    /// page or a single undated card never qualifies.
+    pub fn page_predates_since(&self, cards: &[PriceHit]) -> bool {
+        self.since.is_some_and(|since| {
+            !cards.is_empty()
+                && cards
+                    .iter()
+                    .all(|h| posted_on(h).is_some_and(|d| d < since))
+        })
+    }
+}
+
+/// An `exclude_status` list; anything but active/reserved/sold is an error.
+fn parse_statuses(list: Option<&str>) -> Result<Vec<&'static str>> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            LISTING_STATUSES
+                .iter()
+                .find(|known| **known == s)
+                .copied()
+                .ok_or_else(|| {
+                    anyhow!("unknown exclude_status {s:?}, expected active, reserved or sold")
+                })
+        })
+        .collect()
+}
+
+/// Unbounded passes anything; with either bound set, a missing value fails.
+fn in_range(v: Option<f64>, (min, max): (Option<f64>, Option<f64>)) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+    v.is_some_and(|v| min.is_none_or(|m| v >= m) && max.is_none_or(|m| v <= m))
+}
+
+/// Per-scrape duplicate tracking according to a DedupMode.
+struct SeenHits {
+    mode: DedupMode,
+    ids: HashSet<String>,
+    content: HashSet<u64>,
+    dropped_id: usize,
+    dropped_content: usize,
+}
+
+impl SeenHits {
+    fn new(mode: DedupMode) -> Self {
+        Self {
+            mode,
+            ids: HashSet::new(),
+            content: HashSet::new(),
+            dropped_id: 0,
+            dropped_content: 0,
+        }
+    }
+
+    /// Records the hit; false (and counted as dropped) if it duplicates one
+    /// already seen in this scrape.
+    fn register_hit(&mut self, hit: &PriceHit) -> bool {
+        if matches!(self.mode, DedupMode::Id | DedupMode::Both)
+            && !hit.id.is_empty()
+            && self.ids.contains(&hit.id)
+        {
+            self.dropped_id += 1;
+            return false;
+        }
+        let key = content_key(hit);
+        if matches!(self.mode, DedupMode::Content | DedupMode::Both) && self.content.contains(&key)
+        {
+            self.dropped_content += 1;
+            return false;
+        }
+        self.ids.insert(hit.id.clone());
+        self.content.insert(key);
+        true
+    }
+}
+
+/// Hash of the normalized (title, price_numeric, sqm) triple.
+fn content_key(hit: &PriceHit) -> u64 {
+    let title = hit
+        .title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let key = format!("{}|{:?}|{:?}", title, hit.price_numeric, hit.sqm);
+    fnv1a64(key.as_bytes())
+}
+
+/// Parses and whitelists every URL of a /scrape/pages request. They must all
+/// be on one site: the request holds that site's turn in scrape_slot.
+fn check_page_urls(urls: &[String]) -> Result<Vec<(Url, String, &'static SiteAdapter)>> {
+    if urls.is_empty() {
+        return Err(anyhow!("urls is empty"));
+    }
+    if urls.len() > HARD_PAGE_CAP {
+        return Err(anyhow!("at most {} urls per request", HARD_PAGE_CAP));
+    }
+    let mut page_urls: Vec<(Url, String, &'static SiteAdapter)> = Vec::with_capacity(urls.len());
+    for raw in urls {
+        let u = Url::parse(raw).with_context(|| format!("invalid url: {raw}"))?;
+        let (host, adapter) = whitelisted(&u).with_context(|| format!("rejected url: {raw}"))?;
+        if let Some((_, _, first)) = page_urls.first()
+            && first.hosts[0] != adapter.hosts[0]
+        {
+            return Err(anyhow!(
+                "rejected url: {raw}: all urls must be on one site ({})",
+                first.hosts[0]
+            ));
+        }
+        page_urls.push((u, host, adapter));
+    }
+    Ok(page_urls)
+}
+
+/// Scrapes exactly the given page URLs, without deriving pagination.
+async fn scrape_pages(urls: &[String]) -> Result<(Vec<PriceHit>, Meta)> {
+    let page_urls = check_page_urls(urls)?;
+    // robots.txt per URL, fetched once per origin
+    let mut robots: HashMap<String, String> = HashMap::new();
+    for (u, host, _) in &page_urls {
+        let origin = format!("{}://{}", u.scheme(), host);
+        if !robots.contains_key(&origin) {
+            let robots_txt = fetch_robots(u.scheme(), host).await.unwrap_or_default();
+            robots.insert(origin.clone(), robots_txt);
+        }
+        if !robots_allows(&robots[&origin], u.as_str()) {
+            return Err(anyhow::Error::new(AccessDenied::RobotsDisallowed))
+                .with_context(|| format!("rejected url: {u}"));
+        }
+    }
+
+    let selectors = CardSelectors::new()?;
+    let mut hits: Vec<PriceHit> = Vec::new();
+    let mut seen = SeenHits::new(DedupMode::Id);
+    let mut prev_page_url: Option<Url> = None;
+    let n = page_urls.len();
+    let mut warnings: Vec<String> = Vec::new();
+    let processors = hit_processor_chain(&mut warnings);
+
+    // one client (and cookie jar) for the whole request, warmed up per origin
+    let client = build_client()?;
+    let mut warmed: HashMap<String, Option<WarmupToken>> = HashMap::new();
+
+    for (i, (page_url, host, adapter)) in page_urls.into_iter().enumerate() {
+        let origin = format!("{}://{}", page_url.scheme(), host);
+        if !warmed.contains_key(&origin) {
+            let token = warmup_hit(&client, &origin, FetchOpts::default(), adapter).await;
+            warmed.insert(origin.clone(), token);
+        }
+        let fetch_opts = FetchOpts::default().with_token(warmed[&origin].as_ref());
+
+        let referer = prev_page_url
+            .as_ref()
+            .map(|u| u.as_str().to_string())
+            .unwrap_or_else(|| origin.clone());
+        let html = retry_fetch_html(
+            &client,
+            &page_url,
+            &referer,
+            &adapter.category_page,
+            fetch_opts,
+        )
+        .await?
+        .html;
+
+        let doc = Html::parse_document(&html);
+        let mut page_count = 0usize;
+        let page = normalize_pager(&page_url).1;
+        for mut hit in parse_page_cards(&doc, &page_url, page, &selectors, ParseOpts::default()) {
+            if seen.register_hit(&hit) {
+                for p in &processors {
+                    p.process(&mut hit).await;
+                }
+                hits.push(hit);
+                page_count += 1;
+            }
+        }
+        eprintln!(
+            "[pages] {}/{} page={} cards={} total_hits={}",
+            i + 1,
+            n,
+            page_url,
+            page_count,
+            hits.len()
+        );
+
+        prev_page_url = Some(page_url);
+        if i + 1 < n {
+            sleep(DelayRange::configured().sample()).await;
+            yield_now().await;
+        }
+    }
+
+    let meta = Meta {
+        scraped_at: Some(now_rfc3339()),
+        page_count: n,
+        total_hits: hits.len(),
+        dedup_mode: DedupMode::Id,
+        dropped_duplicate_id: seen.dropped_id,
+        warnings,
+        ..Default::default()
+    };
+    Ok((hits, meta))
+}
+
+/// Fetches one listing detail page and parses it into a ListingDetail.
+async fn scrape_listing(raw_url: &str) -> Result<ListingDetail> {
+    let url = Url::parse(raw_url).context("invalid url")?;
+    let (host, adapter) = ensure_allowed(&url).await?;
+    let origin = format!("{}://{}", url.scheme(), host);
+
+    let client = build_client()?;
+    let token = warmup_hit(&client, &origin, FetchOpts::default(), adapter).await;
+    let html = retry_fetch_html(
+        &client,
+        &url,
+        &origin,
+        &adapter.detail_page,
+        FetchOpts::default().with_token(token.as_ref()),
+    )
+    .await?
+    .html;
+
+    let doc = Html::parse_document(&html);
+    parse_listing_detail(&doc, &url)
+}
+
+/// One category page, read for its pager and result count only.
+async fn scrape_pagination(raw_url: &str) -> Result<PaginationInfo> {
+    let url = Url::parse(raw_url).context("invalid url")?;
+    let (host, adapter) = ensure_allowed(&url).await?;
+    let origin = format!("{}://{}", url.scheme(), host);
+    let (base, page) = normalize_pager(&url);
+    let page_url = build_page_url(&base, page)?;
+
+    let client = build_client()?;
+    let token = warmup_hit(&client, &origin, FetchOpts::default(), adapter).await;
+    let html = retry_fetch_html(
+        &client,
+        &page_url,
+        &origin,
+        &adapter.category_page,
+        FetchOpts::default().with_token(token.as_ref()),
+    )
+    .await?
+    .html;
+
+    let doc = Html::parse_document(&html);
+    let per_page = parse_page_cards(
+        &doc,
+        &page_url,
+        page,
+        &CardSelectors::new()?,
+        ParseOpts::default(),
+    )
+    .len();
+    parse_pagination(&doc, page, per_page)
+}
+
+/// Whitelist + robots.txt gate shared by every scrape entry point. Returns the
+/// host and the adapter that owns it.
+async fn ensure_allowed(url: &Url) -> Result<(String, &'static SiteAdapter)> {
+    let (host, adapter) = whitelisted(url)?;
+
+    let robots_txt = fetch_robots(url.scheme(), &host).await.unwrap_or_default();
+    if !robots_allows(&robots_txt, url.as_str()) {
+        return Err(AccessDenied::RobotsDisallowed.into());
+    }
+    Ok((host, adapter))
+}
+
+/// The domain and path part of ensure_allowed, without robots.txt.
+fn whitelisted(url: &Url) -> Result<(String, &'static SiteAdapter), AccessDenied> {
+    let host = url.host_str().ok_or(AccessDenied::NoHost)?.to_string();
+    let adapter = adapter_for_host(&host).ok_or(AccessDenied::DomainNotAllowed)?;
+    let prefixes = &config().allowed_path_prefixes;
+    if !prefixes.is_empty() && !prefixes.iter().any(|p| url.path().starts_with(p.as_str())) {
+        return Err(AccessDenied::PathNotAllowed);
+    }
+    Ok((host, adapter))
+}
+
+/// The user-agent token robots.txt groups are matched against.
+const ROBOTS_AGENT: &str = "Mozilla";
+
+fn robots_allows(robots_txt: &str, url: &str) -> bool {
+    let mut matcher: DefaultMatcher = DefaultMatcher::default();
+    matcher.one_agent_allowed_by_robots(robots_txt, ROBOTS_AGENT, url)
+}
+
+struct RobotsRule {
+    line: usize,
+    text: String,
+}
+
+/// (line, key, value) of every `key: value` directive, comments stripped and
+/// keys lowercased, each tagged with the user-agents of its group.
+fn robots_directives(robots_txt: &str) -> Vec<(Vec<String>, usize, String, String)> {
+    let mut out = Vec::new();
+    let mut agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+    for (i, line) in robots_txt.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_ascii_lowercase(), value.trim().to_string());
+        if key == "user-agent" {
+            // consecutive user-agent lines share a group; one after rules starts a new one
+            if in_rules {
+                agents.clear();
+                in_rules = false;
+            }
+            agents.push(value.to_ascii_lowercase());
+        } else {
+            in_rules = true;
+            out.push((agents.clone(), i + 1, key, value));
+        }
+    }
+    out
+}
+
+/// Directives of the group robots.txt addresses to ROBOTS_AGENT, or of `*`
+/// when no group names it; the same choice the matcher makes.
+fn robots_group(robots_txt: &str) -> Vec<(usize, String, String)> {
+    let directives = robots_directives(robots_txt);
+    let agent = ROBOTS_AGENT.to_ascii_lowercase();
+    let specific = directives
+        .iter()
+        .any(|(agents, ..)| agents.contains(&agent));
+    let wanted = if specific { agent } else { "*".to_string() };
+    directives
+        .into_iter()
+        .filter(|(agents, ..)| agents.contains(&wanted))
+        .map(|(_, line, key, value)| (line, key, value))
+        .collect()
+}
+
+/// The rule behind a verdict: the longest pattern of the winning kind
+/// (allow/disallow) that matches the path, as the matcher picks it.
+fn robots_rule(robots_txt: &str, path: &str, allowed: bool) -> Option<RobotsRule> {
+    let kind = if allowed { "allow" } else { "disallow" };
+    robots_group(robots_txt)
+        .into_iter()
+        .filter(|(_, key, value)| {
+            key == kind && !value.is_empty() && robots_pattern_matches(value, path)
+        })
+        .max_by_key(|(_, _, value)| value.len())
+        .map(|(line, key, value)| RobotsRule {
+            line,
+            text: format!("{}{}: {}", key[..1].to_ascii_uppercase(), &key[1..], value),
+        })
+}
+
+fn robots_crawl_delay(robots_txt: &str) -> Option<f64> {
+    robots_group(robots_txt)
+        .into_iter()
+        .find(|(_, key, _)| key == "crawl-delay")
+        .and_then(|(_, _, value)| value.parse().ok())
+}
+
+/// robots.txt path patterns: a prefix match where `*` is any run of
+/// characters and a trailing `$` anchors the end.
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let body = pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    let re = format!("^{body}{}", if anchored { "$" } else { "" });
+    Regex::new(&re).is_ok_and(|re| re.is_match(path))
+}
+
+/// Why ensure_allowed rejected a URL.
+#[derive(Debug, Clone, Copy)]
+enum AccessDenied {
+    NoHost,
+    DomainNotAllowed,
+    PathNotAllowed,
+    RobotsDisallowed,
+}
+
+impl AccessDenied {
+    fn code(&self) -> &'static str {
+        match self {
+            AccessDenied::NoHost => "invalid_url",
+            AccessDenied::DomainNotAllowed => "domain_not_allowed",
+            AccessDenied::PathNotAllowed => "path_not_allowed",
+            AccessDenied::RobotsDisallowed => "robots_disallowed",
+        }
+    }
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AccessDenied::NoHost => "url has no host",
+            AccessDenied::DomainNotAllowed => "domain not in whitelist",
+            AccessDenied::PathNotAllowed => "path not under any CLAW_ALLOWED_PATH_PREFIXES entry",
+            AccessDenied::RobotsDisallowed => "robots.txt disallows this URL",
+        })
+    }
+}
+
+impl std::error::Error for AccessDenied {}
+
+/// Failures that are our fault rather than the request's or the site's.
+#[derive(Debug)]
+enum ScrapeError {
+    /// A selector string doesn't compile.
+    Config { selector: String, reason: String },
+}
+
+impl std::fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScrapeError::Config { selector, reason } => {
+                write!(f, "invalid selector {selector:?}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScrapeError {}
+
+/// Selector::parse without the panic; a bad selector becomes ScrapeError::Config.
+fn selector(css: &str) -> Result<Selector> {
+    Selector::parse(css).map_err(|e| {
+        ScrapeError::Config {
+            selector: css.to_string(),
+            reason: e.to_string(),
+        }
+        .into()
+    })
+}
+
+// -------------------------
+// Site adapters
+// -------------------------
+
+/// What a successfully fetched page looks like. Anything else (challenge pages,
+/// truncated bodies, error pages) makes retry_fetch_html try again.
+#[derive(Clone, Copy)]
+struct ContentCheck<'a> {
+    marker: &'a str,
+    /// What the site renders instead of the list when there's nothing to show;
+    /// such a page is a real (empty) answer, not a failed fetch.
+    empty_markers: &'static [&'static str],
+}
+
+impl<'a> ContentCheck<'a> {
+    /// This check judging by `marker` instead; the empty markers stay.
+    fn with_marker(self, marker: &'a str) -> Self {
+        ContentCheck { marker, ..self }
+    }
+
+    /// The marker decides; length only sets how sure we need to be. Pages over
+    /// CLAW_MIN_PAGE_BYTES may carry it anywhere, shorter ones (a category with
+    /// two listings) only count when an element has it as a class name, not
+    /// stray text or a longer class such as `EntityList-items`.
+    fn accepts(&self, html: &str) -> bool {
+        let short = html.len() <= config().min_page_bytes;
+        if !short && html.contains(self.marker) {
+            return true;
+        }
+        // a long page without the marker is only parsed if it may be an empty one
+        if !short && self.empty_markers.is_empty() {
+            return false;
+        }
+        let doc = Html::parse_document(html);
+        (short && has_class(&doc, self.marker)) || self.is_empty_result(&doc)
+    }
+
+    /// Some element carries one of the empty markers as a class name.
+    /// CLAW_NO_RESULTS_MARKERS extend only checks that expect empty results
+    /// at all, i.e. category pages.
+    fn is_empty_result(&self, doc: &Html) -> bool {
+        if self.empty_markers.is_empty() {
+            return false;
+        }
+        self.empty_markers
+            .iter()
+            .copied()
+            .chain(config().no_results_markers.iter().map(String::as_str))
+            .any(|m| has_class(doc, m))
+    }
+}
+
+/// Whether any element carries `class` as a whole class name, however its
+/// `class` attribute is quoted.
+fn has_class(doc: &Html, class: &str) -> bool {
+    doc.root_element()
+        .descendent_elements()
+        .any(|e| e.value().classes().any(|c| c == class))
+}
+
+/// Site-specific knowledge the generic fetch code consults.
+struct SiteAdapter {
+    /// Hosts this adapter serves; together they form the domain whitelist.
+    hosts: &'static [&'static str],
+    /// The site's mobile host. Not whitelisted: only fetch_with_retries goes
+    /// there, as an escalation for a URL on one of `hosts`.
+    mobile_host: Option<&'static str>,
+    category_page: ContentCheck<'static>,
+    detail_page: ContentCheck<'static>,
+    /// Anti-bot token the site plants in the warmup response; None for sites
+    /// that don't, where the warmup only collects cookies.
+    warmup_token: Option<TokenSource>,
+}
+
+/// Where a site hides a per-session token in its pages and the request
+/// header it expects the token back in.
+pub struct TokenSource {
+    /// Regex whose first capture group is the token.
+    pub pattern: &'static str,
+    pub header: &'static str,
+}
+
+/// The TokenSource of the adapter serving `host`, if it has one.
+pub fn token_source_for(host: &str) -> Option<&'static TokenSource> {
+    adapter_for_host(host)?.warmup_token.as_ref()
+}
+
+const ADAPTERS: &[SiteAdapter] = &[
+    // njuskalo.hr
+    SiteAdapter {
+        hosts: &["www.njuskalo.hr", "njuskalo.hr"],
+        mobile_host: Some("m.njuskalo.hr"),
+        category_page: ContentCheck {
+            marker: "EntityList-item",
+            empty_markers: &["EntityList--empty", "search-no-results"],
+        },
+        detail_page: ContentCheck {
+            marker: "ClassifiedDetail",
+            empty_markers: &[],
+        },
+        warmup_token: Some(TokenSource {
+            pattern: r#"<meta[^>]+name=["']csrf-token["'][^>]+content=["']([^"']+)["']"#,
+            header: "x-csrf-token",
+        }),
+    },
+];
+
+fn adapter_for_host(host: &str) -> Option<&'static SiteAdapter> {
+    ADAPTERS.iter().find(|a| a.hosts.contains(&host))
+}
+
+/// The same URL on the adapter's mobile host, if it has one.
+fn mobile_variant(url: &Url) -> Option<Url> {
+    let mobile = adapter_for_host(url.host_str()?)?.mobile_host?;
+    let mut out = url.clone();
+    out.set_host(Some(mobile)).ok()?;
+    Some(out)
+}
+
+// -------------------------
+// Dedup sessions (CLAW_SESSION_TTL_SECS)
+// -------------------------
+
+/// Listing ids reported to one session; the session expires once unused for
+/// CLAW_SESSION_TTL_SECS.
+struct DedupSession {
+    ids: HashSet<String>,
+    last_used: Instant,
+}
+
+static SESSIONS: OnceLock<Mutex<HashMap<String, DedupSession>>> = OnceLock::new();
+
+fn session_header(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("x-claw-session")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Ids already returned to `session`, taken once when a scrape starts.
+/// Expired sessions are dropped here rather than on every claim.
+fn session_seen(session: &str) -> HashSet<String> {
+    let ttl = config().session_ttl;
+    let mut sessions = SESSIONS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    sessions.retain(|_, s| s.last_used.elapsed() < ttl);
+    sessions
+        .get(session)
+        .map(|s| s.ids.clone())
+        .unwrap_or_default()
+}
+
+/// Records `hits` as returned to `session`. Called only with what the client
+/// actually gets, after the scrape succeeded; no-op without a session.
+fn session_claim(session: Option<&str>, hits: &[PriceHit]) {
+    let Some(session) = session else {
+        return;
+    };
+    let mut sessions = SESSIONS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let entry = sessions
+        .entry(session.to_string())
+        .or_insert_with(|| DedupSession {
+            ids: HashSet::new(),
+            last_used: Instant::now(),
+        });
+    entry.last_used = Instant::now();
+    entry.ids.extend(hits.iter().map(|h| h.id.clone()));
+}
+
+// -------------------------
+// Page cache (CLAW_PAGE_CACHE; ETag/Last-Modified revalidation)
+// -------------------------
+
+#[derive(Clone)]
+struct CachedPage {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    html: String,
+    stored: Instant,
+}
+
+impl CachedPage {
+    /// (ETag, Last-Modified) of a response, if it sent either.
+    fn validators_of(headers: &HeaderMap) -> (Option<String>, Option<String>) {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        (get(ETAG), get(LAST_MODIFIED))
+    }
+
+    fn add_validators(&self, headers: &mut HeaderMap) {
+        if let Some(v) = self
+            .etag
+            .as_deref()
+            .and_then(|v| HeaderValue::from_str(v).ok())
+        {
+            headers.insert(IF_NONE_MATCH, v);
+        }
+        if let Some(v) = self
+            .last_modified
+            .as_deref()
+            .and_then(|v| HeaderValue::from_str(v).ok())
+        {
+            headers.insert(IF_MODIFIED_SINCE, v);
+        }
+    }
+}
+
+static PAGE_CACHE: OnceLock<Mutex<HashMap<String, CachedPage>>> = OnceLock::new();
+
+fn page_cache() -> Option<std::sync::MutexGuard<'static, HashMap<String, CachedPage>>> {
+    if !config().page_cache {
+        return None;
+    }
+    let cache = PAGE_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    Some(cache.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+fn page_cache_get(url: &Url) -> Option<CachedPage> {
+    page_cache()?.get(url.as_str()).cloned()
+}
+
+/// Only pages with a validator are worth keeping; without one there's nothing
+/// to revalidate against. Evicts the oldest entry when full.
+fn page_cache_put(url: &Url, (etag, last_modified): (Option<String>, Option<String>), html: &str) {
+    if etag.is_none() && last_modified.is_none() {
+        return;
+    }
+    let Some(mut cache) = page_cache() else {
+        return;
+    };
+    if cache.len() >= config().page_cache_entries
+        && !cache.contains_key(url.as_str())
+        && let Some(oldest) = cache
+            .iter()
+            .min_by_key(|(_, c)| c.stored)
+            .map(|(k, _)| k.clone())
+    {
+        cache.remove(&oldest);
+    }
+    cache.insert(
+        url.to_string(),
+        CachedPage {
+            etag,
+            last_modified,
+            html: html.to_string(),
+            stored: Instant::now(),
+        },
+    );
+}
+
+/// Empties the page cache, returning how many pages it held.
+fn page_cache_clear() -> usize {
+    let Some(cache) = PAGE_CACHE.get() else {
+        return 0;
+    };
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    let n = cache.len();
+    cache.clear();
+    n
+}
+
+/// Entries dropped by POST /admin/cache/clear, per cache. robots.txt is
+/// fetched fresh for every scrape, so there's no robots cache to report.
+#[derive(Serialize, ToSchema)]
+struct CacheCleared {
+    pages: usize,
+}
+
+/// With CLAW_API_TOKEN set, admin endpoints want `Authorization: Bearer <token>`.
+fn check_api_token(req: &HttpRequest) -> Result<(), HttpResponse> {
+    let Some(token) = &config().api_token else {
+        return Ok(());
+    };
+    let sent = req
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    if sent == Some(token.as_str()) {
+        Ok(())
+    } else {
+        Err(HttpResponse::Unauthorized()
+            .insert_header(("WWW-Authenticate", "Bearer"))
+            .json(serde_json::json!({ "error": "missing or invalid API token" })))
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/cache/clear",
+    responses(
+        (status = 200, description = "Entries removed from each in-process cache", body = CacheCleared),
+        (status = 401, description = "CLAW_API_TOKEN is set and the request didn't carry it")
+    )
+)]
+#[post("/admin/cache/clear")]
+async fn admin_cache_clear(req: HttpRequest) -> impl Responder {
+    if let Err(denied) = check_api_token(&req) {
+        return denied;
+    }
+    let cleared = CacheCleared {
+        pages: page_cache_clear(),
+    };
+    eprintln!("[admin] cache cleared: pages={}", cleared.pages);
+    HttpResponse::Ok().json(cleared)
+}
+
+/// Env vars reqwest takes an outbound proxy from; only whether one is set is
+/// reported, their URLs may carry credentials.
+const PROXY_ENV_VARS: &[&str] = &[
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+/// Config as resolved from the environment, defaults and clamping applied,
+/// grouped by concern. Secrets show as whether they're set, never their value.
+fn effective_config() -> serde_json::Value {
+    let cfg = config();
+    let secs = |d: Duration| d.as_secs();
+    let hosts: Vec<&str> = ADAPTERS
+        .iter()
+        .flat_map(|a| a.hosts.iter().copied())
+        .collect();
+    let mobile_hosts: Vec<&str> = ADAPTERS.iter().filter_map(|a| a.mobile_host).collect();
+    let processors: Vec<String> = hit_processor_chain(&mut Vec::new())
+        .iter()
+        .map(|p| p.name().to_string())
+        .collect();
+    serde_json::json!({
+        "server": {
+            "bind_addr": format!("{}:{}", BIND_ADDR.0, BIND_ADDR.1),
+            "base_path": cfg.base_path,
+            "workers": cfg.workers,
+            "api_token_set": cfg.api_token.is_some(),
+            "trust_proxy": cfg.trust_proxy,
+            "default_url": cfg.default_url,
+            "default_page_range": cfg.default_page_range,
+            "profiles_path": cfg.profiles_path,
+        },
+        "access": {
+            "allowed_hosts": hosts,
+            "fallback_hosts": mobile_hosts,
+            "allowed_path_prefixes": cfg.allowed_path_prefixes,
+            "robots": { "enforced": true, "agent": ROBOTS_AGENT, "cached": false },
+        },
+        "limits": {
+            "page_cap": HARD_PAGE_CAP,
+            "max_concurrent_scrapes": cfg.max_concurrent_scrapes,
+            "host_concurrency": cfg.host_concurrency,
+            "host_queue_depth": cfg.host_queue_depth,
+            "client_host_concurrency": cfg.client_host_concurrency,
+            "max_sse_streams": cfg.max_sse_streams,
+            "max_body_bytes": cfg.max_body_bytes,
+            "max_redirects": cfg.max_redirects,
+        },
+        "pacing": {
+            "min_delay_ms": cfg.min_delay_ms,
+            "max_delay_ms": cfg.max_delay_ms,
+            "delay_floor_ms": cfg.delay_floor_ms,
+            "delay_ceiling_ms": cfg.delay_ceiling_ms,
+            "burst_size": cfg.burst_size,
+            "burst_rest_ms": cfg.burst_rest_ms,
+        },
+        "protection": {
+            "block_cooldown_secs": secs(cfg.block_cooldown),
+            "block_cooldown_max_secs": secs(cfg.block_cooldown_max),
+            "breaker_window": cfg.breaker_window,
+            "breaker_min_fetches": cfg.breaker_min_fetches,
+            "breaker_failure_ratio": cfg.breaker_failure_ratio,
+            "breaker_open_secs": secs(cfg.breaker_open),
+        },
+        "cache": {
+            "page_cache": cfg.page_cache,
+            "page_cache_entries": cfg.page_cache_entries,
+            "session_ttl_secs": secs(cfg.session_ttl),
+            "db_path": cfg.db_path,
+        },
+        "http": {
+            "warmup_token": !cfg.no_warmup_token,
+            "proxy_configured": PROXY_ENV_VARS.iter().any(|v| std::env::var_os(v).is_some()),
+            "accept_invalid_certs": cfg.accept_invalid_certs,
+            "fixed_header_order": cfg.fixed_header_order,
+            "rng_seed": cfg.rng_seed,
+            "pool_max_idle_per_host": cfg.pool_max_idle_per_host,
+            "pool_idle_timeout_secs": cfg.pool_idle_timeout.map(secs),
+            "tcp_keepalive_secs": cfg.tcp_keepalive.map(secs),
+            "accept_any_content_type": cfg.accept_any_content_type,
+            "min_page_bytes": cfg.min_page_bytes,
+        },
+        "parsing": {
+            "card_link_selector": cfg.card_link_selector,
+            "status_badge_selector": cfg.status_badge_selector,
+            "no_card_fallback": cfg.no_card_fallback,
+            "no_results_markers": cfg.no_results_markers,
+            "districts_path": cfg.districts_path,
+            "districts": district_table().len(),
+            "trailing_slash": format!("{:?}", cfg.trailing_slash).to_lowercase(),
+            "locale": {
+                "thousands": cfg.locale.thousands,
+                "decimal": cfg.locale.decimal,
+                "symbol_first": cfg.locale.symbol_first,
+            },
+            "hit_processors": processors,
+        },
+        "fixtures": {
+            "record_dir": cfg.record_dir,
+            "replay_dir": cfg.replay_dir,
+        },
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/config",
+    responses(
+        (status = 200, description = "Effective configuration grouped by concern (server, access, limits, pacing, protection, cache, http, parsing, fixtures); secrets reported only as set or not", body = Object),
+        (status = 401, description = "CLAW_API_TOKEN is set and the request didn't carry it")
+    )
+)]
+#[get("/config")]
+async fn config_endpoint(req: HttpRequest) -> impl Responder {
+    if let Err(denied) = check_api_token(&req) {
+        return denied;
+    }
+    HttpResponse::Ok().json(effective_config())
+}
+
+// -------------------------
+// Host cooldown (block detection)
+// -------------------------
+
+/// Substrings of challenge and CAPTCHA pages served instead of content.
+const BLOCK_MARKERS: &[&str] = &[
+    "cf-challenge",
+    "cf-browser-verification",
+    "challenges.cloudflare.com",
+    "Attention Required! | Cloudflare",
+    "g-recaptcha",
+    "h-captcha",
+    "captcha-delivery",
+];
+
+/// A response that was refused rather than just odd: 403/429, or a challenge page.
+fn looks_blocked(status: StatusCode, html: &str) -> bool {
+    status == StatusCode::FORBIDDEN
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || BLOCK_MARKERS.iter().any(|m| html.contains(m))
+}
+
+struct Cooldown {
+    until: Instant,
+    /// Blocks in a row, each doubling the next cooldown; one is forgiven per
+    /// CLAW_BLOCK_COOLDOWN_MAX_SECS without a block.
+    strikes: u32,
+    last_block: Instant,
+}
+
+static COOLDOWNS: OnceLock<Mutex<HashMap<String, Cooldown>>> = OnceLock::new();
+
+fn cooldowns() -> std::sync::MutexGuard<'static, HashMap<String, Cooldown>> {
+    COOLDOWNS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Cooldowns are per site, like the queues in scrape_slot: a block on
+/// www.njuskalo.hr holds off njuskalo.hr too.
+fn cooldown_key(host: &str) -> &str {
+    adapter_for_host(host).map_or(host, |a| a.hosts[0])
+}
+
+/// Strike count and cooldown for a new block, given the previous strikes and
+/// the time since that block, if any: `base` doubled per strike, capped at
+/// `max`. One earlier strike is forgiven per full `max` without a block.
+pub fn next_cooldown(
+    previous: Option<(u32, Duration)>,
+    base: Duration,
+    max: Duration,
+) -> (u32, Duration) {
+    let strikes = previous.map_or(0, |(strikes, since)| {
+        let quiet = since.as_secs() / max.as_secs().max(1);
+        strikes.saturating_sub(u32::try_from(quiet).unwrap_or(u32::MAX))
+    });
+    let strikes = strikes.saturating_add(1);
+    let wait = base.saturating_mul(1 << (strikes - 1).min(16)).min(max);
+    (strikes, wait)
+}
+
+/// Starts (or extends) the cooldown of `host`'s site after a block and returns
+/// its length; see next_cooldown for CLAW_BLOCK_COOLDOWN_SECS and _MAX_SECS.
+fn host_blocked(host: &str) -> Duration {
+    let host = cooldown_key(host);
+    let now = Instant::now();
+    let mut cooldowns = cooldowns();
+    let previous = cooldowns
+        .get(host)
+        .map(|c| (c.strikes, now.duration_since(c.last_block)));
+    let (strikes, wait) = next_cooldown(
+        previous,
+        config().block_cooldown,
+        config().block_cooldown_max,
+    );
+    cooldowns.insert(
+        host.to_string(),
+        Cooldown {
+            until: now + wait,
+            strikes,
+            last_block: now,
+        },
+    );
+    eprintln!(
+        "[block] {} blocked us (strike {}), cooling down for {}s",
+        host,
+        strikes,
+        wait.as_secs()
+    );
+    wait
+}
+
+/// Time left on the cooldown of `host`'s site; None when scrapes may go ahead.
+fn host_cooldown_left(host: &str) -> Option<Duration> {
+    let left = cooldowns()
+        .get(cooldown_key(host))?
+        .until
+        .saturating_duration_since(Instant::now());
+    (!left.is_zero()).then_some(left)
+}
+
+/// The target host is cooling down after blocking us; maps to 429 with Retry-After.
+#[derive(Debug)]
+struct HostCooldown {
+    host: String,
+    retry_after: Duration,
+}
+
+impl std::fmt::Display for HostCooldown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is blocking scrapes, retry in {}s",
+            self.host,
+            retry_after_secs(self.retry_after)
+        )
+    }
+}
+
+impl std::error::Error for HostCooldown {}
+
+/// Whole seconds for Retry-After, rounded up so a client never comes back early.
+fn retry_after_secs(d: Duration) -> u64 {
+    d.as_millis().div_ceil(1000) as u64
+}
+
+fn cooldown_response(c: &HostCooldown) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", retry_after_secs(c.retry_after).to_string()))
+        .json(serde_json::json!({ "error": c.to_string() }))
+}
+
+// -------------------------
+// Circuit breaker (per host)
+// -------------------------
+
+/// Closed: fetches go through and their outcomes fill a rolling window. Open:
+/// fetches fail fast until `until`. HalfOpen: one trial fetch decides.
+enum Breaker {
+    Closed { outcomes: VecDeque<bool> },
+    Open { until: Instant },
+    HalfOpen { trial_started: Instant },
+}
+
+static BREAKERS: OnceLock<Mutex<HashMap<String, Breaker>>> = OnceLock::new();
+
+fn breakers() -> std::sync::MutexGuard<'static, HashMap<String, Breaker>> {
+    BREAKERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Err(CircuitOpen) while `host`'s breaker is open, or half-open with a trial
+/// already out. A trial that never reported back (its scrape was cancelled)
+/// is given up on after CLAW_BREAKER_OPEN_SECS.
+fn breaker_admit(host: &str) -> Result<(), CircuitOpen> {
+    let open_for = config().breaker_open;
+    let now = Instant::now();
+    let mut breakers = breakers();
+    let Some(breaker) = breakers.get_mut(host) else {
+        return Ok(());
+    };
+    match breaker {
+        Breaker::Closed { .. } => Ok(()),
+        Breaker::Open { until } if now < *until => Err(CircuitOpen {
+            host: host.to_string(),
+            retry_after: *until - now,
+        }),
+        Breaker::HalfOpen { trial_started } if now.duration_since(*trial_started) < open_for => {
+            Err(CircuitOpen {
+                host: host.to_string(),
+                retry_after: open_for - now.duration_since(*trial_started),
+            })
+        }
+        _ => {
+            eprintln!("[breaker] {} half-open, sending a trial fetch", host);
+            *breaker = Breaker::HalfOpen { trial_started: now };
+            Ok(())
+        }
+    }
+}
+
+/// Records one page fetch. The breaker opens once CLAW_BREAKER_MIN_FETCHES
+/// outcomes are in and the failure share of the last CLAW_BREAKER_WINDOW
+/// reaches CLAW_BREAKER_FAILURE_RATIO; a half-open trial closes or reopens it.
+fn breaker_record(host: &str, ok: bool) {
+    let c = config();
+    let mut breakers = breakers();
+    let breaker = breakers
+        .entry(host.to_string())
+        .or_insert_with(|| Breaker::Closed {
+            outcomes: VecDeque::new(),
+        });
+    let open = || Breaker::Open {
+        until: Instant::now() + c.breaker_open,
+    };
+    match breaker {
+        Breaker::HalfOpen { .. } if ok => {
+            eprintln!("[breaker] {} recovered, circuit closed", host);
+            *breaker = Breaker::Closed {
+                outcomes: VecDeque::new(),
+            };
+        }
+        Breaker::HalfOpen { .. } => {
+            eprintln!("[breaker] {} trial failed, circuit open again", host);
+            *breaker = open();
+        }
+        Breaker::Closed { outcomes } => {
+            outcomes.push_back(ok);
+            while outcomes.len() > c.breaker_window {
+                outcomes.pop_front();
+            }
+            let failed = outcomes.iter().filter(|ok| !**ok).count();
+            let ratio = failed as f64 / outcomes.len() as f64;
+            if outcomes.len() >= c.breaker_min_fetches && ratio >= c.breaker_failure_ratio {
+                eprintln!(
+                    "[breaker] {} failed {}/{} recent fetches, circuit open for {}s",
+                    host,
+                    failed,
+                    outcomes.len(),
+                    c.breaker_open.as_secs()
+                );
+                *breaker = open();
+            }
+        }
+        // an outcome from a fetch admitted before the circuit opened
+        Breaker::Open { .. } => {}
+    }
+}
+
+/// `host` failed too many recent fetches; maps to 503 with Retry-After.
+#[derive(Debug)]
+struct CircuitOpen {
+    host: String,
+    retry_after: Duration,
+}
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is failing, circuit open; retry in {}s",
+            self.host,
+            retry_after_secs(self.retry_after)
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+// -------------------------
+// Fetch helpers
+// -------------------------
+
+const ROBOTS_TIMEOUT: Duration = Duration::from_secs(10);
+const ROBOTS_MAX_BYTES: usize = 512 * 1024;
+const ROBOTS_SLOW: Duration = Duration::from_secs(2);
+
+/// Fetches robots.txt with its own timeout and size cap. Any failure, or a
+/// body over ROBOTS_MAX_BYTES (almost certainly not a robots file), yields
+/// None; callers treat that as an empty file, which allows everything.
+async fn fetch_robots(scheme: &str, host: &str) -> Option<String> {
+    let robots_url = format!("{}://{}/robots.txt", scheme, host);
+    let started = Instant::now();
+    let body = async {
+        let client = reqwest::Client::builder()
+            .timeout(ROBOTS_TIMEOUT)
+            .redirect(redirect_policy())
+            .danger_accept_invalid_certs(config().accept_invalid_certs)
+            .build()?;
+        let mut rsp = client.get(&robots_url).send().await?;
+        let mut buf: Vec<u8> = Vec::new();
+        while let Some(chunk) = rsp.chunk().await? {
+            if buf.len() + chunk.len() > ROBOTS_MAX_BYTES {
+                eprintln!(
+                    "[robots] {} exceeds {} bytes, ignoring it",
+                    robots_url, ROBOTS_MAX_BYTES
+                );
+                return Ok(None);
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        Ok::<_, reqwest::Error>(Some(buf))
+    }
+    .await;
+
+    let elapsed = started.elapsed();
+    if elapsed > ROBOTS_SLOW {
+        eprintln!("[robots] slow fetch: {} took {:?}", robots_url, elapsed);
+    }
+    match body {
+        Ok(bytes) => bytes.map(|b| String::from_utf8_lossy(&b).into_owned()),
+        Err(e) => {
+            eprintln!("[robots] fetch failed for {}: {e}", robots_url);
+            None
+        }
+    }
+}
+
+/// Client for one scrape job. It has its own cookie jar, so the session the
+/// warmup hit establishes carries into page fetches without leaking across
+/// jobs. Decompression is enabled explicitly to match the Accept-Encoding we
+/// advertise in base_headers, so bodies are always decoded HTML.
+fn build_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(random_desktop_ua())
+        .redirect(redirect_policy())
+        .timeout(Duration::from_secs(25))
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .cookie_store(true)
+        .pool_max_idle_per_host(config().pool_max_idle_per_host)
+        .pool_idle_timeout(config().pool_idle_timeout)
+        .tcp_keepalive(config().tcp_keepalive)
+        .danger_accept_invalid_certs(config().accept_invalid_certs)
+        .build()
+}
+
+/// Follows up to CLAW_MAX_REDIRECTS hops, logging each one so bounces through
+/// consent or login pages show up in the logs.
+fn redirect_policy() -> reqwest::redirect::Policy {
+    let max = config().max_redirects;
+    reqwest::redirect::Policy::custom(move |attempt| {
+        let hops = attempt.previous().len();
+        if hops > max {
+            return attempt.error(format!("more than {max} redirects"));
+        }
+        let from = attempt.previous().last().map_or("?", |u| u.as_str());
+        eprintln!(
+            "[redirect] {} -> {} ({} {}/{})",
+            from,
+            attempt.url(),
+            attempt.status().as_u16(),
+            hops,
+            max
+        );
+        attempt.follow()
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Profile {
+    Desktop,
+    Mobile,
+}
+
+/// Per-scrape tweaks to the headers base_headers picks.
+#[derive(Default, Clone, Copy)]
+struct FetchOpts<'a> {
+    /// Replaces the jittered Accept-Language (ScrapeReq.accept_language).
+    accept_language: Option<&'a HeaderValue>,
+    /// Token the warmup response carried, sent back on every page fetch.
+    token: Option<&'a WarmupToken>,
+}
+
+impl<'a> FetchOpts<'a> {
+    fn apply(&self, headers: &mut HeaderMap) {
+        if let Some(lang) = self.accept_language {
+            headers.insert(ACCEPT_LANGUAGE, lang.clone());
+        }
+        if let Some(token) = self.token {
+            headers.insert(token.header.clone(), token.value.clone());
+        }
+    }
+
+    /// These options plus whatever token warmup_hit found.
+    fn with_token(self, token: Option<&'a WarmupToken>) -> Self {
+        FetchOpts { token, ..self }
+    }
+}
+
+/// An anti-bot token read from a warmup response (see TokenSource).
+pub struct WarmupToken {
+    pub header: HeaderName,
+    pub value: HeaderValue,
+}
+
+/// Longest token we're willing to echo back; anything bigger isn't a nonce.
+const MAX_WARMUP_TOKEN_LEN: usize = 512;
+
+static TOKEN_PATTERNS: OnceLock<Mutex<HashMap<&'static str, Option<Regex>>>> = OnceLock::new();
+
+/// The token `source` finds in a warmup body. None without a source, when
+/// the page carries none, or when it's empty or over MAX_WARMUP_TOKEN_LEN.
+pub fn warmup_token(source: Option<&TokenSource>, body: &str) -> Option<WarmupToken> {
+    let source = source?;
+    // compiled once per pattern; a bad one is logged once and stays None
+    let re = TOKEN_PATTERNS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(source.pattern)
+        .or_insert_with(|| {
+            Regex::new(source.pattern)
+                .map_err(|e| eprintln!("[warmup] bad token pattern {:?}: {e}", source.pattern))
+                .ok()
+        })
+        .clone()?;
+    let token = re.captures(body)?.get(1)?.as_str().trim();
+    if token.is_empty() || token.len() > MAX_WARMUP_TOKEN_LEN {
+        return None;
+    }
+    Some(WarmupToken {
+        header: HeaderName::from_static(source.header),
+        value: HeaderValue::from_str(token).ok()?,
+    })
+}
+
+static ACCEPT_LANGUAGE_RE: OnceLock<Regex> = OnceLock::new();
+
+/// A well-formed Accept-Language value: comma-separated language ranges
+/// (`hr-HR`, `en`, `*`), each with an optional `;q=` weight.
+fn parse_accept_language(value: &str) -> Result<HeaderValue> {
+    let re = ACCEPT_LANGUAGE_RE.get_or_init(|| {
+        Regex::new(
+            r"^(?:\*|[A-Za-z]{1,8}(?:-[A-Za-z0-9]{1,8})*)(?:\s*;\s*q=(?:0(?:\.\d{0,3})?|1(?:\.0{0,3})?))?$",
+        )
+        .unwrap()
+    });
+    let ok = !value.trim().is_empty() && value.split(',').all(|range| re.is_match(range.trim()));
+    if !ok {
+        return Err(anyhow!("invalid accept_language {value:?}"));
+    }
+    HeaderValue::from_str(value.trim()).context("invalid accept_language")
+}
+
+fn base_headers(profile: Profile, referer: &str) -> HeaderMap {
+    let ua = match profile {
+        Profile::Desktop => random_desktop_ua(),
+        Profile::Mobile => random_mobile_ua(),
+    };
+    let static_header =
+        |name: HeaderName, value: &'static str| vec![(name, HeaderValue::from_static(value))];
+    // each group goes out as a unit; only the order of the groups varies
+    let mut groups: Vec<Vec<(HeaderName, HeaderValue)>> = vec![
+        vec![(USER_AGENT, HeaderValue::from_str(&ua).unwrap())],
+        static_header(
+            ACCEPT,
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        ),
+        vec![(
+            ACCEPT_LANGUAGE,
+            HeaderValue::from_str(&jittered_accept_language()).unwrap(),
+        )],
+        static_header(ACCEPT_ENCODING, "gzip, deflate, br"),
+        vec![(REFERER, HeaderValue::from_str(referer).unwrap())],
+        static_header(UPGRADE_INSECURE_REQUESTS, "1"),
+        static_header(CONNECTION, "keep-alive"),
+        static_header(CACHE_CONTROL, "max-age=0"),
+    ];
+
+    // real browsers differ here (privacy settings, extensions), so don't always send them
+    let (send_pragma, send_dnt) = with_jitter_rng(|r| (r.random_bool(0.7), r.random_bool(0.6)));
+    if send_pragma {
+        groups.push(static_header(PRAGMA, "no-cache"));
+    }
+    if send_dnt {
+        groups.push(static_header(DNT, "1"));
+    }
+
+    let hints: Vec<_> = client_hints(&ua)
+        .into_iter()
+        .map(|(name, value)| {
+            (
+                HeaderName::from_static(name),
+                HeaderValue::from_str(&value).unwrap(),
+            )
+        })
+        .collect();
+    if !hints.is_empty() {
+        groups.push(hints);
+    }
+
+    let fetch_site = if referer == SEARCH_ENGINE_REFERER {
+        "cross-site"
+    } else {
+        "same-origin"
+    };
+    groups.push(vec![
+        (
+            HeaderName::from_static("sec-fetch-site"),
+            HeaderValue::from_static(fetch_site),
+        ),
+        (
+            HeaderName::from_static("sec-fetch-mode"),
+            HeaderValue::from_static("navigate"),
+        ),
+        (
+            HeaderName::from_static("sec-fetch-dest"),
+            HeaderValue::from_static("document"),
+        ),
+    ]);
+
+    // a fixed insertion order is a fingerprint of its own; HeaderMap keeps it
+    if !config().fixed_header_order {
+        with_jitter_rng(|r| groups.shuffle(r));
+    }
+    let mut h = HeaderMap::new();
+    for (name, value) in groups.into_iter().flatten() {
+        h.insert(name, value);
+    }
+    h
+}
+
+/// Croatian-first Accept-Language with q-values drawn from plausible ranges;
+/// the en-US entry is sometimes left out as browsers configured for hr only do.
+fn jittered_accept_language() -> String {
+    with_jitter_rng(|r| {
+        let q_hr = r.random_range(8..=9);
+        let q_en = r.random_range(5..q_hr);
+        if r.random_bool(0.6) {
+            let q_en_us = r.random_range(q_en + 1..=q_hr);
+            format!("hr-HR,hr;q=0.{q_hr},en-US;q=0.{q_en_us},en;q=0.{q_en}")
+        } else {
+            format!("hr-HR,hr;q=0.{q_hr},en;q=0.{q_en}")
+        }
+    })
+}
+
+/// sec-ch-ua hints consistent with the UA string. Only Chromium sends them;
+/// Safari and Firefox UAs get none, as in real traffic.
+fn client_hints(ua: &str) -> Vec<(&'static str, String)> {
+    let Some(major) = ua
+        .split("Chrome/")
+        .nth(1)
+        .and_then(|v| v.split('.').next())
+        .filter(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+    else {
+        return Vec::new();
+    };
+    let mobile = ua.contains("Mobile");
+    let platform = if ua.contains("Android") {
+        "Android"
+    } else if ua.contains("Windows") {
+        "Windows"
+    } else if ua.contains("Mac OS X") {
+        "macOS"
+    } else {
+        "Linux"
+    };
+    const GREASE: &[&str] = &[
+        r#""Not-A.Brand";v="99""#,
+        r#""Not A(Brand";v="99""#,
+        r#""Not/A)Brand";v="8""#,
+    ];
+    let grease = GREASE[with_jitter_rng(|r| r.random_range(0..GREASE.len()))];
+    vec![
+        (
+            "sec-ch-ua",
+            format!(r#""Chromium";v="{major}", "Google Chrome";v="{major}", {grease}"#),
+        ),
+        (
+            "sec-ch-ua-mobile",
+            if mobile { "?1" } else { "?0" }.to_string(),
+        ),
+        ("sec-ch-ua-platform", format!(r#""{platform}""#)),
+    ]
+}
+
+/// GETs the origin so the cookie jar holds a session before the first page,
+/// and picks up the adapter's anti-bot token from the body if it plants one.
+async fn warmup_hit(
+    client: &reqwest::Client,
+    origin: &str,
+    opts: FetchOpts<'_>,
+    adapter: &SiteAdapter,
+) -> Option<WarmupToken> {
+    let mut headers = base_headers(Profile::Desktop, origin);
+    opts.apply(&mut headers);
+    match client.get(origin).headers(headers).send().await {
+        Ok(r) => {
+            if config().no_warmup_token || adapter.warmup_token.is_none() {
+                return None;
+            }
+            let body = match read_body_limited(r, config().max_body_bytes).await {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("[warmup] body not read for a token: {e:#}");
+                    return None;
+                }
+            };
+            let token = warmup_token(adapter.warmup_token.as_ref(), &body);
+            if let Some(t) = &token {
+                eprintln!(
+                    "[warmup] {} token found ({} bytes), sending it on page fetches",
+                    t.header,
+                    t.value.len()
+                );
+            }
+            token
+        }
+        Err(e) => {
+            eprintln!("[warmup] failed: {e}");
+            None
+        }
+    }
+}
+
+/// A page body that passed the content check, plus what the server told us about it.
+struct FetchedPage {
+    html: String,
+    status: u16,
+    /// URL after redirects; differs from the requested one when we got bounced.
+    final_url: Url,
+    /// 304 Not Modified: `html` is the cached copy from an earlier fetch.
+    unchanged: bool,
+    /// Requests it took, 1 when the first one passed; 0 for a replayed fixture.
+    attempts: u32,
+}
+
+/// Every attempt got a page, none passed the content check and none looked
+/// like a block: short, truncated or marker-less pages.
+#[derive(Debug)]
+struct PageRejected {
+    url: String,
+}
+
+impl std::fmt::Display for PageRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: no attempt passed the content check", self.url)
+    }
+}
+
+impl std::error::Error for PageRejected {}
+
+/// A fetch failure that `empty_page_tolerance` treats as one more empty page
+/// rather than the end of the scrape.
+fn counts_as_empty_page(e: &anyhow::Error) -> bool {
+    e.is::<PageRejected>() || e.is::<HostCooldown>()
+}
+
+/// Response body exceeded CLAW_MAX_BODY_BYTES.
+#[derive(Debug)]
+struct BodyTooLarge {
+    limit: usize,
+}
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "response body exceeds {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// Reads the (decompressed) body chunk by chunk, giving up with BodyTooLarge
+/// past `limit` bytes instead of buffering whatever the host sends. Decodes
+/// with the Content-Type charset like `Response::text` does.
+async fn read_body_limited(mut rsp: reqwest::Response, limit: usize) -> Result<String> {
+    if rsp.content_length().is_some_and(|n| n as usize > limit) {
+        return Err(BodyTooLarge { limit }.into());
+    }
+    let content_type = rsp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let mut buf: Vec<u8> = Vec::new();
+    while let Some(chunk) = rsp.chunk().await? {
+        if buf.len() + chunk.len() > limit {
+            return Err(BodyTooLarge { limit }.into());
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(decode_html(&buf, content_type.as_deref()))
+}
+
+static META_CHARSET_RE: OnceLock<Regex> = OnceLock::new();
+
+/// How far into the body a `<meta charset>` is looked for, as browsers do.
+const META_CHARSET_SCAN: usize = 1024;
+
+/// Decodes a page body. A BOM wins, then the Content-Type charset, then a
+/// `<meta charset>` / `http-equiv` declaration near the top. Undeclared bytes
+/// that aren't valid UTF-8 are read as windows-1250, the legacy Croatian
+/// code page, so č/ć/š/ž/đ survive instead of turning into U+FFFD.
+pub fn decode_html(bytes: &[u8], content_type: Option<&str>) -> String {
+    // parameter names are case-insensitive, like the `<meta>` pattern below
+    let charset_of = |text: &str| {
+        text.split(';')
+            .filter_map(|p| p.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+            .map(|(_, c)| c.trim().trim_matches(['"', '\'']).to_string())
+    };
+    let declared = content_type.and_then(charset_of).or_else(|| {
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(META_CHARSET_SCAN)]);
+        let re = META_CHARSET_RE
+            .get_or_init(|| Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([\w-]+)"#).unwrap());
+        re.captures(&head).map(|c| c[1].to_string())
+    });
+    let encoding = match declared
+        .as_deref()
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+    {
+        Some(e) => e,
+        None if std::str::from_utf8(bytes).is_err() => {
+            eprintln!("[fetch] undeclared non-UTF-8 body, decoding as windows-1250");
+            encoding_rs::WINDOWS_1250
+        }
+        None => encoding_rs::UTF_8,
+    };
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+/// `text/html` or `application/xhtml+xml`, parameters ignored.
+fn is_html_content_type(ct: &HeaderValue) -> bool {
+    let mime = ct.to_str().unwrap_or("").split(';').next().unwrap_or("");
+    let mime = mime.trim().to_ascii_lowercase();
+    mime == "text/html" || mime == "application/xhtml+xml"
+}
+
+/// Failed attempts before odd-numbered retries go to the mobile subdomain.
+const MOBILE_ESCALATE_AFTER: u32 = 2;
+
+/// A page from CLAW_REPLAY_DIR if there is one, else fetched with retries
+/// behind the host's circuit breaker; a tripped breaker fails at once.
+async fn retry_fetch_html(
+    client: &reqwest::Client,
+    page_url: &Url,
+    referer: &str,
+    check: &ContentCheck<'_>,
+    opts: FetchOpts<'_>,
+) -> Result<FetchedPage> {
+    if let Some(dir) = &config().replay_dir {
+        let path = fixture_path(dir, page_url);
+        if let Ok(bytes) = std::fs::read(&path) {
+            eprintln!("[replay] {} <- {}", page_url, path.display());
+            let html = decode_html(&bytes, None);
+            return Ok(FetchedPage {
+                html,
+                status: 200,
+                final_url: page_url.clone(),
+                unchanged: false,
+                attempts: 0,
+            });
+        }
+    }
+
+    let host = page_url.host_str().unwrap_or_default();
+    breaker_admit(host)?;
+    let result = fetch_with_retries(client, page_url, referer, check, opts).await;
+    match &result {
+        Ok(_) => breaker_record(host, true),
+        // the page's size says nothing about the host's health
+        Err(e) if e.is::<BodyTooLarge>() => {}
+        Err(_) => breaker_record(host, false),
+    }
+    result
+}
+
+/// Up to five attempts at one page, flipping desktop/mobile profiles and
+/// escalating to the mobile host; see retry_fetch_html.
+async fn fetch_with_retries(
+    client: &reqwest::Client,
+    page_url: &Url,
+    referer: &str,
+    check: &ContentCheck<'_>,
+    opts: FetchOpts<'_>,
+) -> Result<FetchedPage> {
+    let mut attempts = 0;
+    let mut last_err: Option<anyhow::Error> = None;
+    let mut profile = Profile::Desktop;
+
+    // escalation target for attempts 3 and 5; robots.txt is checked on first use
+    let mut mobile: Option<Option<Url>> = None;
+    let mut blocked = false;
+
+    while attempts < 5 {
+        attempts += 1;
+        let mut fetch_url = page_url;
+        let mut fetch_profile = profile;
+        if attempts > MOBILE_ESCALATE_AFTER && attempts % 2 == 1 {
+            if mobile.is_none() {
+                // the mobile host is off the whitelist, so only robots.txt applies
+                let candidate = match mobile_variant(page_url) {
+                    Some(m) => {
+                        let robots_txt = fetch_robots(m.scheme(), m.host_str().unwrap_or_default())
+                            .await
+                            .unwrap_or_default();
+                        robots_allows(&robots_txt, m.as_str()).then_some(m)
+                    }
+                    None => None,
+                };
+                mobile = Some(candidate);
+            }
+            if let Some(Some(m)) = &mobile {
+                eprintln!("[fetch] {} escalating to mobile host {}", page_url, m);
+                fetch_url = m;
+                fetch_profile = Profile::Mobile;
+            }
+        }
+        let mut headers = base_headers(fetch_profile, referer);
+        opts.apply(&mut headers);
+        let cached = if fetch_url == page_url {
+            page_cache_get(page_url)
+        } else {
+            None
+        };
+        if let Some(c) = &cached {
+            c.add_validators(&mut headers);
+        }
+        let resp = client.get(fetch_url.as_str()).headers(headers).send().await;
+
+        match resp {
+            Ok(rsp) => {
+                // Capture these BEFORE reading the body (which consumes the response)
+                let status = rsp.status();
+                let final_url = rsp.url().clone();
+                if status == StatusCode::NOT_MODIFIED
+                    && let Some(c) = cached
+                {
+                    eprintln!("[fetch] {} -> 304, reusing cached page", page_url);
+                    return Ok(FetchedPage {
+                        html: c.html,
+                        status: status.as_u16(),
+                        final_url,
+                        unchanged: true,
+                        attempts,
+                    });
+                }
+                // a non-HTML body is left unread, but its status still goes
+                // through block detection below: WAFs and rate limiters
+                // answer 403/429 in JSON or plain text
+                let foreign_type = rsp
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .filter(|ct| !config().accept_any_content_type && !is_html_content_type(ct))
+                    .cloned();
+                let validators = CachedPage::validators_of(rsp.headers());
+                let text = if let Some(ct) = &foreign_type {
+                    eprintln!(
+                        "[fetch] {} via={} -> status={} final={} content-type={:?}, not HTML",
+                        page_url, fetch_url, status, final_url, ct
+                    );
+                    last_err = Some(anyhow!(
+                        "{final_url} answered with non-HTML content type {ct:?}"
+                    ));
+                    String::new()
+                } else {
+                    match read_body_limited(rsp, config().max_body_bytes).await {
+                        Ok(t) => t,
+                        // the same URL will be just as large next time, don't retry
+                        Err(e) if e.is::<BodyTooLarge>() => return Err(e),
+                        Err(e) => {
+                            eprintln!("[fetch] {} body read failed: {e:#}", page_url);
+                            last_err = Some(e);
+                            String::new()
+                        }
+                    }
+                };
+                let len = text.len();
+
+                eprintln!(
+                    "[fetch] {} via={} profile={:?} -> status={} final={} len={} (referer={})",
+                    page_url, fetch_url, fetch_profile, status, final_url, len, referer
+                );
+
+                // `text` is already decompressed, so the check applies to the HTML itself
+                if foreign_type.is_none() && check.accepts(&text) {
+                    if let Some(dir) = &config().record_dir {
+                        record_fixture(dir, page_url, &text);
+                    }
+                    if fetch_url == page_url {
+                        page_cache_put(page_url, validators, &text);
+                    }
+                    return Ok(FetchedPage {
+                        html: text,
+                        status: status.as_u16(),
+                        final_url,
+                        unchanged: false,
+                        attempts,
+                    });
+                }
+
+                if looks_blocked(status, &text) {
+                    eprintln!("[block] {} looks like a block page ({})", fetch_url, status);
+                    blocked = true;
+                }
+
+                // Not good enough → flip profile and back off
+                profile = match profile {
+                    Profile::Desktop => Profile::Mobile,
+                    Profile::Mobile => Profile::Desktop,
+                };
+                sleep(Duration::from_millis(rng().random_range(600..1500))).await;
+            }
+            Err(e) => {
+                last_err = Some(e.into());
+                sleep(Duration::from_millis(rng().random_range(600..1500))).await;
+            }
+        }
+    }
+
+    if blocked && let Some(host) = page_url.host_str() {
+        let retry_after = host_blocked(host);
+        return Err(HostCooldown {
+            host: cooldown_key(host).to_string(),
+            retry_after,
+        }
+        .into());
+    }
+    Err(last_err.unwrap_or_else(|| {
+        PageRejected {
+            url: page_url.to_string(),
+        }
+        .into()
+    }))
+}
+
+/// `<dir>/<sanitized-url>.html`: scheme dropped, anything but `[A-Za-z0-9.-]`
+/// becomes `_`. Long URLs are cut and suffixed with a hash to stay unique.
+fn fixture_path(dir: &Path, url: &Url) -> PathBuf {
+    let raw = url.as_str();
+    let rest = raw.split_once("://").map_or(raw, |(_, r)| r);
+    let mut name: String = rest
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.len() > 180 {
+        name.truncate(180);
+        name.push_str(&format!("_{:016x}", fnv1a64(raw.as_bytes())));
+    }
+    dir.join(format!("{name}.html"))
+}
+
+/// Best effort: a failed write is logged and the scrape carries on.
+fn record_fixture(dir: &Path, url: &Url, html: &str) {
+    let path = fixture_path(dir, url);
+    let res = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, html));
+    match res {
+        Ok(()) => eprintln!("[record] {} -> {}", url, path.display()),
+        Err(e) => eprintln!("[record] {} write to {} failed: {e}", url, path.display()),
+    }
+}
+
+// -------------------------
+// Parsing helpers
+// -------------------------
+
+struct CardSelectors {
+    list_section: Selector,
+    list_ul: Selector,
+    li_item: Selector,
+    body: Selector,
+    title_a: Selector,
+    price: Selector,
+    desc_main: Selector,
+    badges: Selector,
+    /// CLAW_STATUS_BADGE_SELECTOR, or the built-in badge/label classes.
+    status_badge: Selector,
+    pub_date: Selector,
+    images: Selector,
+    image_badge: Selector,
+    price_attr: Selector,
+    /// CLAW_CARD_LINK_SELECTOR, tried before the built-in link sources.
+    custom_link: Option<Selector>,
+    oglas_link: Selector,
+    data_url: Selector,
+    onclick: Selector,
+    /// Where the fallback card search stays when the page marks it out.
+    main_region: Selector,
+}
+
+impl CardSelectors {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            list_section: selector("section.EntityList")?,
+            list_ul: selector("ul.EntityList-items")?,
+            li_item: selector("li.EntityList-item")?,
+            body: selector("article.entity-body")?,
+            title_a: selector("h3.entity-title > a.link")?,
+            price: selector(
+                "div.entity-prices strong.price, div.entity-prices del, div.entity-prices s, div.entity-prices [class*=\"price--old\"]",
+            )?,
+            desc_main: selector(".entity-description-main")?,
+            badges: selector(
+                ".entity-flag, .entity-badge, .badge, .label, [class*=\"novogradnja\"], [class*=\"new-build\"]",
+            )?,
+            status_badge: selector(config().status_badge_selector.as_deref().unwrap_or(
+                ".entity-flag, .entity-badge, .badge, .label, [class*=\"sold\"], [class*=\"reserved\"], [class*=\"prodan\"], [class*=\"rezerv\"]",
+            ))?,
+            pub_date: selector("time[datetime], .entity-pub-date, [class*=\"pub-date\"]")?,
+            images: selector("img[src], img[data-src], [data-src]")?,
+            price_attr: selector(
+                "[data-price], [data-price-value], [itemprop=\"price\"][content]",
+            )?,
+            image_badge: selector(
+                "[class*=\"image-count\"], [class*=\"photo-count\"], [class*=\"gallery-count\"]",
+            )?,
+            custom_link: config()
+                .card_link_selector
+                .as_deref()
+                .map(selector)
+                .transpose()?,
+            oglas_link: selector("a[href*=\"oglas\"]")?,
+            data_url: selector("[data-url]")?,
+            onclick: selector("[onclick]")?,
+            main_region: selector("main, [role=\"main\"], #content, .content-main")?,
+        })
+    }
+}
+
+/// Every card of a category page's HTML, as a scrape would see it (page 1).
+pub fn parse_listing_html(html: &str, page_url: &Url) -> Result<Vec<PriceHit>> {
+    let selectors = CardSelectors::new()?;
+    Ok(parse_page_cards(
+        &Html::parse_document(html),
+        page_url,
+        1,
+        &selectors,
+        ParseOpts::default(),
+    ))
+}
+
+/// Per-request knobs of parse_page_cards.
+#[derive(Clone, Copy, Default)]
+struct ParseOpts {
+    /// Fill PriceHit.raw_text.
+    include_text: bool,
+    /// Look at no more than this many cards of the page.
+    max_per_page: Option<usize>,
+}
+
+/// All cards on a page, tagged with its page number. When the structured
+/// EntityList path finds none, falls back to any `li.EntityList-item` in the
+/// main content region (the whole document without one), unless
+/// CLAW_NO_CARD_FALLBACK is set.
+fn parse_page_cards(
+    doc: &Html,
+    page_url: &Url,
+    page: usize,
+    sel: &CardSelectors,
+    opts: ParseOpts,
+) -> Vec<PriceHit> {
+    let limit = opts.max_per_page.unwrap_or(usize::MAX);
+    let parse = |items: &mut dyn Iterator<Item = scraper::ElementRef>| -> Vec<PriceHit> {
+        items
+            .take(limit)
+            .filter_map(|li| parse_card(&li, page_url, page, sel, opts.include_text))
+            .collect()
+    };
+    let cards = parse(
+        &mut doc
+            .select(&sel.list_section)
+            .flat_map(|section| section.select(&sel.list_ul))
+            .flat_map(|ul| ul.select(&sel.li_item)),
+    );
+    if !cards.is_empty() || config().no_card_fallback {
+        return cards;
+    }
+    // nav and footer lists can carry the same class; stay inside the main
+    // content region when one holds any items
+    let region = doc
+        .select(&sel.main_region)
+        .find(|r| r.select(&sel.li_item).next().is_some());
+    let (cards, scope) = match region {
+        Some(r) => (parse(&mut r.select(&sel.li_item)), "main region"),
+        None => (parse(&mut doc.select(&sel.li_item)), "whole document"),
+    };
+    if !cards.is_empty() {
+        eprintln!(
+            "[parse] page={} primary path found 0 cards, fallback ({}) recovered {}",
+            page,
+            scope,
+            cards.len()
+        );
+    }
+    cards
+}
+
+static TOTAL_LISTINGS_SEL: OnceLock<Selector> = OnceLock::new();
+
+/// The site's own result count from the category header ("1.234 oglasa"):
+/// thousands separators dropped, first number wins. What the site claims,
+/// not what a scrape will find.
+pub fn extract_total_listings(doc: &Html) -> Option<usize> {
+    let sel = TOTAL_LISTINGS_SEL.get_or_init(|| {
+        Selector::parse(".entities-count, .EntityListFilter-count, [class*=\"results-count\"]")
+            .unwrap()
+    });
+    doc.select(sel).find_map(|e| {
+        let text = e
+            .text()
+            .collect::<String>()
+            .replace(['.', ' ', '\u{a0}'], "");
+        let digits: String = text
+            .chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        digits.parse::<usize>().ok()
+    })
+}
+
+/// Best effort, in order: the highest page number linked from the pager, then
+/// total_listings / per_page, then "one page past this one if there's a next link".
+fn parse_pagination(doc: &Html, page: usize, per_page: usize) -> Result<PaginationInfo> {
+    let links = selector(
+        "nav.Pagination a, .Pagination-item a, ul.Pagination-items a, a[rel=\"next\"], link[rel=\"next\"]",
+    )?;
+    let next = selector("a[rel=\"next\"], link[rel=\"next\"], .Pagination-item--next a")?;
+
+    let mut last_page: Option<usize> = None;
+    for a in doc.select(&links) {
+        let from_attr = a
+            .value()
+            .attr("data-page")
+            .and_then(|v| v.trim().parse().ok());
+        let from_href = a.value().attr("href").and_then(|h| {
+            h.split(['?', '&'])
+                .find_map(|kv| kv.strip_prefix("page="))
+                .and_then(|v| v.parse().ok())
+        });
+        let from_text = a.text().collect::<String>().trim().parse().ok();
+        if let Some(n) = from_attr.or(from_href).or(from_text) {
+            last_page = Some(last_page.map_or(n, |m: usize| m.max(n)));
+        }
+    }
+
+    let total_listings = extract_total_listings(doc);
+    let per_page = (per_page > 0).then_some(per_page);
+
+    let mut info = PaginationInfo {
+        total_pages: last_page.map(|n| n.max(page)),
+        total_listings,
+        per_page,
+        estimated: false,
+    };
+    if info.total_pages.is_none() {
+        info.estimated = true;
+        info.total_pages = match (total_listings, per_page) {
+            (Some(t), Some(p)) => Some(t.div_ceil(p).max(1)),
+            _ if doc.select(&next).next().is_some() => Some(page + 1),
+            _ if per_page.is_some() => Some(page),
+            _ => None,
+        };
+    }
+    Ok(info)
+}
+
+fn parse_card(
+    li: &scraper::ElementRef,
+    page_url: &Url,
+    page: usize,
+    sel: &CardSelectors,
+    include_text: bool,
+) -> Option<PriceHit> {
+    let scope = li.select(&sel.body).next().unwrap_or(*li);
+    let title = scope
+        .select(&sel.title_a)
+        .next()
+        .map(|e| e.text().collect::<String>().trim().to_string())
+        .unwrap_or_default();
+
+    let (raw_price, raw_struck) = card_prices(&scope, sel);
+
+    let listing_url = card_link(li, &scope, sel, page_url)
+        .map(|u| u.to_string())
+        .unwrap_or_default();
+
+    let attr_price = card_attr_price(li, sel);
+    if listing_url.is_empty() || (raw_price.is_empty() && attr_price.is_none()) {
+        return None;
+    }
+    let raw_price = match &attr_price {
+        Some(a) if raw_price.is_empty() => a.raw.clone(),
+        _ => raw_price,
+    };
+
+    let mut flags: Vec<String> = Vec::new();
+    let mut id = extract_id(&listing_url);
+    if id.is_empty() {
+        id = format!("{:016x}", fnv1a64(listing_url.as_bytes()));
+        flags.push(FLAG_ID_HASHED.to_string());
+    }
+    // a numeric attribute beats parsing the (possibly decorated) visible text
+    let (price_numeric, currency, price_confidence) = match attr_price {
+        Some(a) => {
+            flags.push(FLAG_PRICE_FROM_ATTRIBUTE.to_string());
+            let currency = a.currency.or_else(|| {
+                // only the defaulted-currency caveat carries over from the text
+                let mut text_flags = Vec::new();
+                let c = normalize_price(&raw_price, &mut text_flags).1;
+                if c.is_none() || text_flags.iter().any(|f| f == FLAG_CURRENCY_DEFAULTED) {
+                    flags.push(FLAG_CURRENCY_DEFAULTED.to_string());
+                }
+                c.or_else(|| Some("EUR".to_string()))
+            });
+            (Some(a.value), currency, Some(CONFIDENCE_ATTRIBUTE))
+        }
+        None => {
+            let (p, c) = normalize_price(&raw_price, &mut flags);
+            if p.is_some() {
+                flags.push(FLAG_PRICE_FROM_TEXT.to_string());
+            }
+            let confidence = p.map(|_| {
+                if flags.iter().any(|f| f == FLAG_CURRENCY_DEFAULTED) {
+                    CONFIDENCE_FALLBACK
+                } else {
+                    CONFIDENCE_UNIT_MATCHED
+                }
+            });
+            (p, c, confidence)
+        }
+    };
+    // the highest struck-through value, and only if it's above the current price
+    let original_price = raw_struck
+        .iter()
+        .filter_map(|r| normalize_price(r, &mut Vec::new()).0)
+        .filter(|o| price_numeric.is_some_and(|p| *o > p))
+        .reduce(f64::max);
+    let discount_pct = match (original_price, price_numeric) {
+        (Some(o), Some(p)) => Some(((o - p) / o * 1000.0).round() / 10.0),
+        _ => None,
+    };
+    let attributes = extract_attributes(li, &sel.desc_main);
+    let labeled_sqm = attribute(&attributes, |k| {
+        k.contains("površina") && !k.contains("okućnic") && !k.contains("zemljišt")
+    })
+    .and_then(parse_sqm);
+    let (sqm, sqm_confidence) = match labeled_sqm
+        .map(|(v, _)| (v, false))
+        .or_else(|| extract_sqm_from_li(li, &sel.desc_main))
+        .or_else(|| extract_sqm_from_li(&scope, &sel.desc_main))
+    {
+        Some((v, true)) => {
+            flags.push(FLAG_SQM_INFERRED.to_string());
+            (Some(v), Some(CONFIDENCE_FALLBACK))
+        }
+        Some((v, false)) => (Some(v), Some(CONFIDENCE_UNIT_MATCHED)),
+        None => (None, None),
+    };
+    let price_period = price_period(&raw_price);
+    let price_per_m2 = match (price_numeric, sqm) {
+        (Some(p), Some(s)) if s > 0.0 && price_period.is_none() => Some(p / s),
+        _ => None,
+    };
+    let price_per_m2_rounded = price_per_m2.map(|v| v.round() as i64);
+    let (price_display, price_per_m2_display) =
+        price_displays(price_numeric, price_per_m2_rounded, currency.as_deref());
+
+    // badge/label markup first, the title only as a fallback
+    let new_build = if has_new_build_badge(li, &sel.badges) {
+        true
+    } else if title.to_lowercase().contains("novogradnja") {
+        flags.push(FLAG_NEW_BUILD_FROM_TITLE.to_string());
+        true
+    } else {
+        false
+    };
+
+    let image_count = extract_image_count(li, sel);
+    let district = lookup_district(
+        attribute(&attributes, |k| k == "lokacija").unwrap_or(&title),
+        district_table(),
+    )
+    .map(str::to_string);
+
+    Some(PriceHit {
+        id,
+        page,
+        listing_url,
+        title,
+        price_numeric,
+        price_confidence,
+        currency,
+        raw_price,
+        price_period: price_period.map(str::to_string),
+        original_price,
+        discount_pct,
+        sqm,
+        sqm_confidence,
+        rooms: attribute(&attributes, |k| {
+            k.starts_with("broj soba") || k.starts_with("broj prostorija")
+        })
+        .and_then(|v| {
+            v.split(|c: char| c.is_whitespace() || c == '-')
+                .find_map(parse_hr_number)
+        }),
+        floor: attribute(&attributes, |k| k == "kat" || k == "etaža").map(str::to_string),
+        district,
+        attributes,
+        price_per_m2,
+        price_per_m2_rounded,
+        price_display,
+        price_per_m2_display,
+        new_build,
+        image_count,
+        status: Some(listing_status(li, &sel.status_badge).to_string()),
+        posted_date: li
+            .select(&sel.pub_date)
+            .find_map(|e| {
+                e.value()
+                    .attr("datetime")
+                    .and_then(parse_posted_date)
+                    .or_else(|| parse_posted_date(&e.text().collect::<String>()))
+            })
+            .map(|d| d.to_string()),
+        flags,
+        raw_text: include_text.then(|| card_text(li)),
+    })
+}
+
+/// Every text node of the card, whitespace runs collapsed to one space.
+fn card_text(li: &scraper::ElementRef) -> String {
+    li.text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn price_displays(
+    price: Option<f64>,
+    per_m2_rounded: Option<i64>,
+    currency: Option<&str>,
+) -> (Option<String>, Option<String>) {
+    let locale = &config().locale;
+    (
+        price.map(|p| locale.money(p, currency, "")),
+        per_m2_rounded.map(|p| locale.money(p as f64, currency, "/m²")),
+    )
+}
+
+/// Key of the hit in Meta.currency_breakdown.
+fn currency_key(hit: &PriceHit) -> String {
+    match &hit.currency {
+        Some(c)
+            if hit.price_numeric.is_some()
+                && !hit.flags.iter().any(|f| f == FLAG_CURRENCY_DEFAULTED) =>
+        {
+            c.clone()
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Restates every amount of the hit in `to`; hits in another or no currency stay as they are.
+fn convert_hit(hit: &mut PriceHit, to: Currency) {
+    let Some(from) = hit.currency.as_deref().and_then(Currency::from_code) else {
+        return;
+    };
+    if from == to {
+        return;
+    }
+    let rate = from.rate_to(to);
+    let round2 = |v: f64| (v * rate * 100.0).round() / 100.0;
+    hit.price_numeric = hit.price_numeric.map(round2);
+    hit.original_price = hit.original_price.map(round2);
+    hit.price_per_m2 = hit.price_per_m2.map(|v| v * rate);
+    hit.price_per_m2_rounded = hit.price_per_m2.map(|v| v.round() as i64);
+    hit.currency = Some(to.code().to_string());
+    (hit.price_display, hit.price_per_m2_display) = price_displays(
+        hit.price_numeric,
+        hit.price_per_m2_rounded,
+        hit.currency.as_deref(),
+    );
+    hit.flags.push(FLAG_CURRENCY_CONVERTED.to_string());
+}
+
+/// "monthly" when the price text carries a per-month suffix ("/mj",
+/// "/ mjesec", "mjesečno"); None for a one-off price.
+fn price_period(raw_price: &str) -> Option<&'static str> {
+    let lower: String = raw_price.to_lowercase().split_whitespace().collect();
+    ["/mj", "mjesečno", "mjesecno"]
+        .iter()
+        .any(|m| lower.contains(m))
+        .then_some("monthly")
+}
+
+/// (current, struck) raw price texts. Every price node in the card is looked
+/// at: struck-through ones (`<del>`, `<s>`, `price--old`, or inside either) are
+/// candidates for the original, the first other one is the current price. With
+/// only struck nodes, the first of them stands in as the current price.
+fn card_prices(scope: &scraper::ElementRef, sel: &CardSelectors) -> (String, Vec<String>) {
+    let mut current: Option<String> = None;
+    let mut struck: Vec<String> = Vec::new();
+    for node in scope.select(&sel.price) {
+        let text = node.text().collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            continue;
+        }
+        let is_struck = matches!(node.value().name(), "del" | "s")
+            || node
+                .value()
+                .attr("class")
+                .is_some_and(|c| c.contains("price--old"))
+            || node
+                .ancestors()
+                .filter_map(scraper::ElementRef::wrap)
+                .any(|a| matches!(a.value().name(), "del" | "s"));
+        if is_struck {
+            if !struck.contains(&text) {
+                struck.push(text);
+            }
+        } else if current.is_none() {
+            current = Some(text);
+        }
+    }
+    match current {
+        Some(c) => (c, struck),
+        None if !struck.is_empty() => (struck.remove(0), struck),
+        None => (String::new(), struck),
+    }
+}
+
+static ONCLICK_URL_RE: OnceLock<Regex> = OnceLock::new();
+
+/// The listing URL, from the first source that yields a usable http(s) link:
+/// CLAW_CARD_LINK_SELECTOR (if set) → title anchor → li `data-href` → any
+/// `a[href*=oglas]` in the card → `data-url` on the li or inside it → a
+/// `location.href = '...'` / `window.open('...')` onclick handler.
+fn card_link(
+    li: &scraper::ElementRef,
+    scope: &scraper::ElementRef,
+    sel: &CardSelectors,
+    page_url: &Url,
+) -> Option<Url> {
+    let first_attr = |s: &Selector, attr: &str| -> Option<String> {
+        li.select(s)
+            .find_map(|e| e.value().attr(attr))
+            .map(str::to_string)
+    };
+    let self_or_inner = |s: &Selector, attr: &str| -> Option<String> {
+        li.value()
+            .attr(attr)
+            .map(str::to_string)
+            .or_else(|| first_attr(s, attr))
+    };
+    let onclick = || {
+        let re = ONCLICK_URL_RE.get_or_init(|| {
+            Regex::new(r#"(?:location(?:\.href)?\s*=|window\.open\s*\()\s*['"]([^'"]+)['"]"#)
+                .unwrap()
+        });
+        std::iter::once(*li)
+            .chain(li.select(&sel.onclick))
+            .filter_map(|e| e.value().attr("onclick"))
+            .find_map(|js| re.captures(js).map(|c| c[1].to_string()))
+    };
+    let sources: [&dyn Fn() -> Option<String>; 6] = [
+        &|| {
+            sel.custom_link.as_ref().and_then(|s| {
+                li.select(s)
+                    .find_map(|e| {
+                        e.value()
+                            .attr("href")
+                            .or_else(|| e.value().attr("data-url"))
+                    })
+                    .map(str::to_string)
+            })
+        },
+        &|| {
+            scope
+                .select(&sel.title_a)
+                .next()
+                .and_then(|a| a.value().attr("href"))
+                .map(str::to_string)
+        },
+        &|| li.value().attr("data-href").map(str::to_string),
+        &|| first_attr(&sel.oglas_link, "href"),
+        &|| self_or_inner(&sel.data_url, "data-url"),
+        &onclick,
+    ];
+    sources.iter().find_map(|source| {
+        let href = source()?;
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') {
+            return None;
+        }
+        page_url
+            .join(href)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+    })
+}
+
+/// A price read from markup attributes rather than text.
+struct AttrPrice {
+    value: f64,
+    raw: String,
+    /// From a sibling `data-currency` / `itemprop="priceCurrency"`, if any.
+    currency: Option<String>,
+}
+
+/// First `data-price`, `data-price-value` or schema.org `itemprop="price"`
+/// value in the card (the li itself included) that parses as a plain number.
+fn card_attr_price(li: &scraper::ElementRef, sel: &CardSelectors) -> Option<AttrPrice> {
+    std::iter::once(*li)
+        .chain(li.select(&sel.price_attr))
+        .find_map(|e| {
+            let el = e.value();
+            let raw = el
+                .attr("data-price")
+                .or_else(|| el.attr("data-price-value"))
+                .or_else(|| {
+                    (el.attr("itemprop") == Some("price"))
+                        .then(|| el.attr("content"))
+                        .flatten()
+                })?
+                .trim();
+            let value = raw
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)?;
+            let currency = el
+                .attr("data-currency")
+                .map(|c| c.trim().to_ascii_uppercase())
+                .filter(|c| !c.is_empty());
+            Some(AttrPrice {
+                value,
+                raw: raw.to_string(),
+                currency,
+            })
+        })
+}
+
+/// Gallery badge ("12", "12 slika") first; it covers photos the card doesn't render.
+fn extract_image_count(li: &scraper::ElementRef, sel: &CardSelectors) -> Option<usize> {
+    let badge = li.select(&sel.image_badge).find_map(|b| {
+        let digits: String = b
+            .text()
+            .collect::<String>()
+            .chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        digits.parse().ok()
+    });
+    badge.or_else(|| Some(li.select(&sel.images).count()).filter(|n| *n > 0))
+}
+
+/// A badge/label whose text or class marks the card as new construction.
+fn has_new_build_badge(li: &scraper::ElementRef, badges: &Selector) -> bool {
+    li.select(badges).any(|b| {
+        let class = b.value().attr("class").unwrap_or("").to_lowercase();
+        let text = b.text().collect::<String>().to_lowercase();
+        class.contains("novogradnja") || class.contains("new-build") || text.contains("novogradnja")
+    })
+}
+
+/// A date as cards and requests write it: ISO `2025-03-12` (a datetime's
+/// leading date included) or Croatian `12.03.2025.`, possibly after a label
+/// like "Objavljen:".
+pub fn parse_posted_date(s: &str) -> Option<time::Date> {
+    static DATE_RE: OnceLock<Regex> = OnceLock::new();
+    let re = DATE_RE.get_or_init(|| {
+        Regex::new(r"(\d{4})-(\d{1,2})-(\d{1,2})|(\d{1,2})\.\s*(\d{1,2})\.\s*(\d{4})").unwrap()
+    });
+    let caps = re.captures(s)?;
+    let num = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<i32>().ok());
+    let (year, month, day) = match num(1) {
+        Some(y) => (y, num(2)?, num(3)?),
+        None => (num(6)?, num(5)?, num(4)?),
+    };
+    let month = time::Month::try_from(u8::try_from(month).ok()?).ok()?;
+    time::Date::from_calendar_date(year, month, u8::try_from(day).ok()?).ok()
+}
+
+/// PriceHit.posted_date back as a date.
+fn posted_on(hit: &PriceHit) -> Option<time::Date> {
+    hit.posted_date.as_deref().and_then(parse_posted_date)
+}
+
+/// PriceHit.status values, what `exclude_status` may name.
+const LISTING_STATUSES: &[&str] = &["active", "reserved", "sold"];
+
+/// "sold" or "reserved" from a badge's text or class ("Prodano",
+/// "Rezervirano", `sold`...), else "active". Sold wins over reserved.
+fn listing_status(li: &scraper::ElementRef, badges: &Selector) -> &'static str {
+    let mut status = "active";
+    for b in li.select(badges) {
+        let class = b.value().attr("class").unwrap_or("").to_lowercase();
+        let text = b.text().collect::<String>().to_lowercase();
+        let says = |words: &[&str]| words.iter().any(|w| class.contains(w) || text.contains(w));
+        if says(&["prodan", "sold"]) {
+            return "sold";
+        }
+        if says(&["rezervir", "reserved"]) {
+            status = "reserved";
+        }
+    }
+    status
+}
+
+fn parse_listing_detail(doc: &Html, url: &Url) -> Result<ListingDetail> {
+    let first_text = |css: &str| -> Result<Option<String>> {
+        Ok(doc
+            .select(&selector(css)?)
+            .next()
+            .map(|e| e.text().collect::<Vec<_>>().join(" "))
+            .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|t| !t.is_empty()))
+    };
+    let meta_content = |css: &str| -> Result<Option<String>> {
+        Ok(doc
+            .select(&selector(css)?)
+            .next()
+            .and_then(|e| e.value().attr("content"))
+            .map(|s| s.trim().to_string()))
+    };
+
+    // every selector is compiled up front, so a broken one errors out even
+    // when an earlier fallback already matched
+    let title = first_text("h1.ClassifiedDetailSummary-title")?
+        .or(first_text("h1")?)
+        .or(meta_content(r#"meta[property="og:title"]"#)?)
+        .unwrap_or_default();
+    let raw_price = first_text(".ClassifiedDetailSummary-priceDomestic")?
+        .or(first_text(".ClassifiedDetailSummary-price")?)
+        .unwrap_or_default();
+    let mut ignored_flags: Vec<String> = Vec::new();
+    let (price_numeric, currency) = if raw_price.is_empty() {
+        (None, None)
+    } else {
+        normalize_price(&raw_price, &mut ignored_flags)
+    };
+    let description = first_text(".ClassifiedDetailDescription-text")?
+        .or(meta_content(r#"meta[name="description"]"#)?)
+        .unwrap_or_default();
+
+    let dt_sel = selector(".ClassifiedDetailBasicDetails-list dt")?;
+    let dd_sel = selector(".ClassifiedDetailBasicDetails-list dd")?;
+    let clean = |e: scraper::ElementRef| {
+        e.text()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let details: Vec<(String, String)> = doc
+        .select(&dt_sel)
+        .map(clean)
+        .zip(doc.select(&dd_sel).map(clean))
+        .collect();
+    let area_m2 = details
+        .iter()
+        .find(|(k, _)| k.to_lowercase().contains("površina"))
+        .and_then(|(_, v)| {
+            v.split_whitespace()
+                .next()
+                .and_then(|n| n.replace('.', "").replace(',', ".").parse::<f64>().ok())
+        });
+
+    let img_sel = selector(".ClassifiedDetailGallery img")?;
+    let og_img_sel = selector(r#"meta[property="og:image"]"#)?;
+    let mut images: Vec<String> = Vec::new();
+    let candidates = doc
+        .select(&img_sel)
+        .filter_map(|img| {
+            img.value()
+                .attr("data-src")
+                .or_else(|| img.value().attr("src"))
+        })
+        .chain(
+            doc.select(&og_img_sel)
+                .filter_map(|m| m.value().attr("content")),
+        );
+    for src in candidates {
+        if let Ok(abs) = url.join(src.trim()) {
+            let abs = abs.to_string();
+            if !images.contains(&abs) {
+                images.push(abs);
+            }
+        }
+    }
+
+    Ok(ListingDetail {
+        id: extract_id(url.as_str()),
+        url: url.to_string(),
+        title,
+        raw_price,
+        price_numeric,
+        currency,
+        area_m2,
+        description,
+        images,
+        details,
+    })
+}
+
+/// Shortest trailing digit run extract_id accepts without an `-oglas-`
+/// marker; shorter ones are page numbers and slug fragments ("…-2"), not ids.
+const MIN_FALLBACK_ID_DIGITS: usize = 6;
+
+/// The listing id of a listing URL, or "" when there's none to trust (the
+/// caller hashes the URL instead). Only the path counts, so the slug, query
+/// and fragment don't matter: the digits after the last `-oglas-` win, then a
+/// trailing run of at least MIN_FALLBACK_ID_DIGITS digits.
+pub fn extract_id(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = path.trim_end_matches('/');
+    if let Some(pos) = path.rfind("-oglas-") {
+        let digits: String = path[pos + 7..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if !digits.is_empty() {
+            return digits;
+        }
+    }
+    let digits = path.len() - path.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits >= MIN_FALLBACK_ID_DIGITS {
+        path[path.len() - digits..].to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Returns the first number in the description and whether it was inferred,
+/// i.e. not directly followed by an m²/m2 unit.
+pub fn extract_sqm_from_li(
+    node: &scraper::ElementRef,
+    desc_main: &Selector,
+) -> Option<(f64, bool)> {
+    let txt = node
+        .select(desc_main)
+        .next()
+        .map(|n| n.text().collect::<String>())?;
+    parse_sqm(&txt)
+}
+
+/// "Key: value" lines of the card description. Lines end at `<br>` and at
+/// block elements (`li`, `p`, `div`), so both `A: 1<br>B: 2` and
+/// `<li><span>A:</span> 1</li>` work; lines without a colon are skipped.
+pub fn extract_attributes(
+    node: &scraper::ElementRef,
+    desc_main: &Selector,
+) -> BTreeMap<String, String> {
+    let mut text = String::new();
+    if let Some(desc) = node.select(desc_main).next() {
+        for n in desc.descendants() {
+            match n.value() {
+                scraper::Node::Text(t) => text.push_str(t),
+                scraper::Node::Element(e) if matches!(e.name(), "br" | "li" | "p" | "div") => {
+                    text.push('\n')
+                }
+                _ => {}
+            }
+        }
+    }
+    text.lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let key = key.split_whitespace().collect::<Vec<_>>().join(" ");
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!key.is_empty() && !value.is_empty()).then_some((key, value))
+        })
+        .collect()
+}
+
+/// Reads a district table: a JSON object of location substring → district,
+/// e.g. `{"knežija": "Trešnjevka - jug"}` (data/zagreb_districts.json is one
+/// for Zagreb). Keys are lowercased and sorted longest first, so
+/// "gornja dubrava" is tried before "dubrava".
+pub fn load_district_table(path: &Path) -> Result<Vec<(String, String)>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("reading districts {}", path.display()))?;
+    let map: BTreeMap<String, String> = serde_json::from_str(&text)
+        .with_context(|| format!("parsing districts {}", path.display()))?;
+    let mut table: Vec<(String, String)> = map
+        .into_iter()
+        .map(|(k, v)| (k.trim().to_lowercase(), v))
+        .filter(|(k, _)| !k.is_empty())
+        .collect();
+    table.sort_by_key(|(k, _)| std::cmp::Reverse(k.chars().count()));
+    Ok(table)
+}
+
+/// District of the first (longest) table key found in `location`, ignoring case.
+pub fn lookup_district<'a>(location: &str, table: &'a [(String, String)]) -> Option<&'a str> {
+    let location = location.to_lowercase();
+    table
+        .iter()
+        .find(|(key, _)| location.contains(key.as_str()))
+        .map(|(_, district)| district.as_str())
+}
+
+static DISTRICTS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// CLAW_DISTRICTS_PATH, loaded once; empty when unset or unreadable.
+fn district_table() -> &'static [(String, String)] {
+    DISTRICTS.get_or_init(|| {
+        let Some(path) = &config().districts_path else {
+            return Vec::new();
+        };
+        match load_district_table(path) {
+            Ok(table) => {
+                eprintln!(
+                    "[districts] {} entries from {}",
+                    table.len(),
+                    path.display()
+                );
+                table
+            }
+            Err(e) => {
+                eprintln!("[districts] {e:#}, districts disabled");
+                Vec::new()
+            }
+        }
+    })
+}
+
+/// Value of the first attribute whose lowercased key passes `key`.
+fn attribute(attributes: &BTreeMap<String, String>, key: impl Fn(&str) -> bool) -> Option<&str> {
+    attributes
+        .iter()
+        .find(|(k, _)| key(&k.to_lowercase()))
+        .map(|(_, v)| v.as_str())
+}
+
+static SQM_RE: OnceLock<Regex> = OnceLock::new();
+
+/// (m², inferred). A number followed by m², m2 or ㎡, with or without a space
+/// ("55 m²", "55m2", "55,5㎡"), wins; otherwise the first bare number is taken
+/// and marked inferred.
+pub fn parse_sqm(text: &str) -> Option<(f64, bool)> {
+    let re = SQM_RE.get_or_init(|| Regex::new(r"(?i)(\d+(?:[.,]\d+)*)\s*(?:m²|m2|㎡)").unwrap());
+    if let Some(v) = re.captures_iter(text).find_map(|c| parse_hr_number(&c[1])) {
+        return Some((v, false));
+    }
+    text.split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .find_map(parse_hr_number)
+        .map(|v| (v, true))
+}
+
+/// Croatian number formatting: `,` is the decimal mark, `.` groups thousands
+/// ("1.234,5"). A lone `.` not followed by exactly three digits is read as a
+/// decimal point instead ("55.5").
+fn parse_hr_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let cleaned = if s.contains(',') {
+        s.replace('.', "").replace(',', ".")
+    } else if s.split('.').skip(1).all(|group| group.len() == 3) {
+        s.replace('.', "")
+    } else {
+        s.to_string()
+    };
+    cleaned.parse::<f64>().ok()
+}
+
+/// (amount, currency) from a card's price text; `€` is EUR, `kn` HRK. Caveats
+/// (price on request, defaulted currency) are pushed onto `flags`.
+pub fn normalize_price(s: &str, flags: &mut Vec<String>) -> (Option<f64>, Option<String>) {
+    let mut cur = None;
+    if s.contains('€') {
+        cur = Some("EUR".to_string());
+    } else if s.to_lowercase().contains("kn") {
+        cur = Some("HRK".to_string());
+    }
+
+    if !s.chars().any(|c| c.is_ascii_digit()) {
+        flags.push(FLAG_PRICE_ON_REQUEST.to_string());
+        return (None, cur);
+    }
+
+    if cur.is_none() {
+        cur = Some("EUR".to_string());
+        flags.push(FLAG_CURRENCY_DEFAULTED.to_string());
+    }
+
+    let digits: String = s
+        .chars()
+        .map(|c| {
+            if c.is_ascii_digit() || c == ',' || c == '.' {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .replace('.', "")
+        .replace(',', ".");
+    let n = digits
+        .split_whitespace()
+        .find_map(|t| t.parse::<f64>().ok());
+    (n, cur)
+}
+
+/// Number formatting for the *_display fields (CLAW_LOCALE).
+#[derive(Debug)]
+struct NumberLocale {
+    thousands: char,
+    decimal: char,
+    /// "€185,000" rather than "185.000 €".
+    symbol_first: bool,
+}
+
+impl NumberLocale {
+    /// hr, de, … group with `.`; en with `,` and a leading symbol; fr with a
+    /// narrow no-break space. Unknown tags fall back to hr-HR.
+    fn from_tag(tag: &str) -> Option<Self> {
+        let lang = tag
+            .split(['-', '_'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let (thousands, decimal, symbol_first) = match lang.as_str() {
+            "hr" | "de" | "sl" | "sr" | "bs" | "it" | "es" | "nl" => ('.', ',', false),
+            "en" => (',', '.', true),
+            "fr" => ('\u{202f}', ',', false),
+            _ => return None,
+        };
+        Some(Self {
+            thousands,
+            decimal,
+            symbol_first,
+        })
+    }
+
+    /// Whole amounts print without decimals, anything else with two.
+    fn number(&self, v: f64) -> String {
+        let cents = (v.abs() * 100.0).round() as u64;
+        let (whole, frac) = (cents / 100, cents % 100);
+        let digits = whole.to_string();
+        let mut out = String::new();
+        for (i, d) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                out.push(self.thousands);
+            }
+            out.push(d);
+        }
+        if frac != 0 {
+            out.push(self.decimal);
+            out.push_str(&format!("{frac:02}"));
+        }
+        if v < 0.0 && cents != 0 {
+            out.insert(0, '-');
+        }
+        out
+    }
+
+    fn money(&self, v: f64, currency: Option<&str>, unit_suffix: &str) -> String {
+        let symbol = match currency {
+            Some("EUR") => "€",
+            Some("HRK") => "kn",
+            Some(other) => other,
+            None => "",
+        };
+        let n = self.number(v);
+        match (symbol, self.symbol_first) {
+            ("", _) => format!("{n}{unit_suffix}"),
+            (sym, true) => format!("{sym}{n}{unit_suffix}"),
+            (sym, false) => format!("{n} {sym}{unit_suffix}"),
+        }
+    }
+}
+
+/// FNV-1a, used for ids that have to stay stable across runs.
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+// -------------------------
+// Pager helpers (page=N scheme)
+// -------------------------
+
+/// (base, start page): the category URL to page through and the `page` it
+/// starts at (1 without one). The query stays as given, `page` included;
+/// build_page_url swaps its value in place.
+pub fn normalize_pager(url: &Url) -> (Url, usize) {
+    let mut base = url.clone();
+
+    let mut start_page: usize = 1;
+    if let Some(q) = base.query() {
+        for kv in q.split('&') {
+            if let Some(v) = kv.strip_prefix("page=")
+                && let Ok(n) = v.parse::<usize>()
+            {
+                start_page = n.max(1);
+            }
+        }
+    }
+
+    config().trailing_slash.apply(&mut base);
+
+    (base, start_page)
+}
+
+/// What CLAW_TRAILING_SLASH does to a category path before `?page=N` goes on;
+/// some paths redirect to their other spelling and lose the query on the way.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TrailingSlash {
+    /// Leave the path as given (default).
+    Keep,
+    /// `/prodaja-stanova/zagreb` → `/prodaja-stanova/zagreb/`
+    Add,
+    /// `/prodaja-stanova/zagreb/` → `/prodaja-stanova/zagreb`
+    Strip,
+}
+
+impl TrailingSlash {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "keep" => Some(TrailingSlash::Keep),
+            "add" => Some(TrailingSlash::Add),
+            "strip" => Some(TrailingSlash::Strip),
+            _ => None,
+        }
+    }
+
+    /// The root path `/` is never touched.
+    fn apply(self, url: &mut Url) {
+        let path = url.path();
+        if path == "/" {
+            return;
+        }
+        let canonical = match self {
+            TrailingSlash::Keep => return,
+            TrailingSlash::Add if !path.ends_with('/') => format!("{path}/"),
+            TrailingSlash::Strip if path.ends_with('/') => path.trim_end_matches('/').to_string(),
+            _ => return,
+        };
+        url.set_path(&canonical);
+    }
+}
+
+/// A redirect that lost the `page` we asked for lands on page 1 again; logs
+/// it and returns the warning for Meta. None when nothing was lost.
+fn page_param_dropped(requested: &Url, final_url: &Url) -> Option<String> {
+    let page_of = |u: &Url| {
+        u.query_pairs()
+            .find(|(k, _)| k == "page")
+            .map(|(_, v)| v.into_owned())
+    };
+    let asked = page_of(requested)?;
+    if page_of(final_url).as_deref() == Some(asked.as_str()) {
+        return None;
+    }
+    eprintln!(
+        "[pager] redirect dropped page={} ({} -> {}); see CLAW_TRAILING_SLASH",
+        asked, requested, final_url
+    );
+    Some(format!(
+        "redirect from {requested} to {final_url} dropped page={asked}"
+    ))
+}
+
+static NEXT_LINK_SEL: OnceLock<Selector> = OnceLock::new();
+
+/// The pager's "next" link: `rel=next` (link or anchor) first, then a
+/// next-item in the Pagination list. None on the last page.
+fn find_next_page_url(doc: &Html, page_url: &Url) -> Option<Url> {
+    let sel = NEXT_LINK_SEL.get_or_init(|| {
+        Selector::parse(
+            "link[rel=next][href], a[rel=next][href], .Pagination-item--next a[href], a.Pagination-link--next[href]",
+        )
+        .unwrap()
+    });
+    doc.select(sel)
+        .filter_map(|e| e.value().attr("href"))
+        .find_map(|href| page_url.join(href.trim()).ok())
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+/// `base` with `page=N`: an existing `page` pair gets the new value where it
+/// stands (later duplicates are dropped), otherwise it's appended. Every other
+/// pair is kept byte for byte; re-serializing would reorder or re-encode them.
+pub fn build_page_url(base: &Url, page: usize) -> Result<Url> {
+    let page_pair = format!("page={page}");
+    let mut replaced = false;
+    let mut pairs: Vec<&str> = Vec::new();
+    for kv in base
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|kv| !kv.is_empty())
+    {
+        if kv == "page" || kv.starts_with("page=") {
+            if !replaced {
+                pairs.push(&page_pair);
+                replaced = true;
+            }
+        } else {
+            pairs.push(kv);
+        }
+    }
+    if !replaced {
+        pairs.push(&page_pair);
+    }
+    let mut u = base.clone();
+    u.set_query(Some(&pairs.join("&")));
+    Ok(u)
+}
+
+// -------------------------
+// Listing history (SQLite)
+// -------------------------
+
+static HISTORY: OnceLock<Result<Mutex<Connection>, String>> = OnceLock::new();
+
+/// Shared connection to the history DB, opened (and migrated) on first use.
+fn history() -> Result<&'static Mutex<Connection>> {
+    HISTORY
+        .get_or_init(|| {
+            let path = config()
+                .db_path
+                .as_deref()
+                .ok_or("listing history is disabled, set CLAW_DB_PATH")?;
+            open_history(path)
+                .map(Mutex::new)
+                .map_err(|e| format!("{e:#}"))
+        })
+        .as_ref()
+        .map_err(|e| anyhow!("{e}"))
+}
+
+fn open_history(path: &str) -> Result<Connection> {
+    let conn = Connection::open(path).with_context(|| format!("cannot open history db {path}"))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS listings (
+            id            TEXT PRIMARY KEY,
+            listing_url   TEXT NOT NULL,
+            title         TEXT NOT NULL,
+            price_numeric REAL,
+            currency      TEXT,
+            first_seen    INTEGER NOT NULL,
+            last_seen     INTEGER NOT NULL
+        );",
+    )?;
+    Ok(conn)
+}
+
+/// Compares hits with the stored history and records them. Returns only hits
+/// that are new or whose price moved since they were last stored.
+fn diff_and_record(hits: Vec<PriceHit>) -> Result<Vec<HitChange>> {
+    let mut conn = history()?
+        .lock()
+        .map_err(|_| anyhow!("history db lock poisoned"))?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+
+    let tx = conn.transaction()?;
+    let mut changes: Vec<HitChange> = Vec::new();
+    {
+        let mut select = tx.prepare("SELECT price_numeric FROM listings WHERE id = ?1")?;
+        let mut upsert = tx.prepare(
+            "INSERT INTO listings (id, listing_url, title, price_numeric, currency, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+                listing_url = excluded.listing_url,
+                title = excluded.title,
+                price_numeric = excluded.price_numeric,
+                currency = excluded.currency,
+                last_seen = excluded.last_seen",
+        )?;
+        for hit in hits {
+            if hit.id.is_empty() {
+                continue;
+            }
+            let stored: Option<Option<f64>> =
+                select.query_row([&hit.id], |row| row.get(0)).optional()?;
+            let change = match (stored, hit.price_numeric) {
+                (None, _) => Some((ChangeType::New, None)),
+                (Some(Some(old)), Some(new)) if new < old => {
+                    Some((ChangeType::PriceDrop, Some(old)))
+                }
+                (Some(Some(old)), Some(new)) if new > old => {
+                    Some((ChangeType::PriceRise, Some(old)))
+                }
+                _ => None,
+            };
+            upsert.execute(rusqlite::params![
+                hit.id,
+                hit.listing_url,
+                hit.title,
+                hit.price_numeric,
+                hit.currency,
+                now
+            ])?;
+            if let Some((change_type, old_price)) = change {
+                changes.push(HitChange {
+                    change_type,
+                    old_price,
+                    hit,
+                });
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(changes)
+}
+
+// -------------------------
+// Runtime configuration (env)
+// -------------------------
+
+struct Config {
+    /// CLAW_DANGER_ACCEPT_INVALID_CERTS: skip TLS verification. Only for local
+    /// fixture servers with self-signed certs, never in production.
+    accept_invalid_certs: bool,
+    /// CLAW_DB_PATH: SQLite file backing the listing history; history
+    /// features are disabled when unset.
+    db_path: Option<String>,
+    /// CLAW_RNG_SEED: seeds UA choice and header jitter for reproducible runs.
+    rng_seed: Option<u64>,
+    /// CLAW_FIXED_HEADER_ORDER: send request headers in one fixed order instead
+    /// of shuffling them per request (header groups like sec-fetch-* stay together).
+    fixed_header_order: bool,
+    /// CLAW_MAX_BODY_BYTES: cap on a fetched page body (default 10MB).
+    max_body_bytes: usize,
+    /// CLAW_WORKERS: actix worker threads (default: one per CPU core).
+    workers: Option<usize>,
+    /// CLAW_MAX_CONCURRENT_SCRAPES: scrapes in flight across all workers (default 4).
+    max_concurrent_scrapes: usize,
+    /// CLAW_DEFAULT_URL: category URL the dashboard form starts with.
+    default_url: String,
+    /// CLAW_DEFAULT_PAGE_RANGE: page_range the dashboard form starts with (default 10).
+    default_page_range: usize,
+    /// CLAW_BASE_PATH: URL prefix when served under a reverse-proxy subpath,
+    /// e.g. `/claw`; normalized to a leading and no trailing slash ("" = root).
+    base_path: String,
+    /// CLAW_ALLOWED_PATH_PREFIXES: comma-separated; when set, a URL's path must
+    /// start with one of them, e.g. `/prodaja-stanova/`.
+    allowed_path_prefixes: Vec<String>,
+    /// CLAW_NO_RESULTS_MARKERS: comma-separated extra class names that mark a
+    /// category page as "no results", on top of the adapter's built-in ones.
+    no_results_markers: Vec<String>,
+    /// CLAW_CARD_LINK_SELECTOR: CSS selector for a card's listing link (its
+    /// `href` or `data-url`), tried before the built-in sources.
+    card_link_selector: Option<String>,
+    /// CLAW_STATUS_BADGE_SELECTOR: CSS selector for the badges whose text or
+    /// class tells a sold/reserved card apart ("Prodano", "Rezervirano").
+    status_badge_selector: Option<String>,
+    /// CLAW_HOST_CONCURRENCY: scrapes of one site (all hosts of its adapter) in
+    /// flight before later ones queue up in arrival order (default:
+    /// CLAW_MAX_CONCURRENT_SCRAPES).
+    host_concurrency: usize,
+    /// CLAW_HOST_QUEUE_DEPTH: requests allowed to wait per site; beyond it 503 (default 16).
+    host_queue_depth: usize,
+    /// CLAW_CLIENT_HOST_CONCURRENCY: scrapes one client may run against a site
+    /// at once, queued in a lane of its own ahead of the site queue; 0 turns
+    /// per-client lanes off (default).
+    client_host_concurrency: usize,
+    /// CLAW_TRUST_PROXY: identify clients by X-Forwarded-For; only behind a
+    /// proxy that sets it, anyone can send the header.
+    trust_proxy: bool,
+    /// CLAW_MAX_SSE_STREAMS: /scrape/stream and /scrape/ws connections producing at once;
+    /// past it a stream gets a single `error` event and closes (default 16).
+    max_sse_streams: usize,
+    /// CLAW_MIN_DELAY_MS / CLAW_MAX_DELAY_MS: bounds of the random pause
+    /// between category pages (default 900 and 2200).
+    min_delay_ms: u64,
+    max_delay_ms: u64,
+    /// CLAW_BURST_SIZE: default burst_size, pages per burst before a long
+    /// rest; 0 paces every page alike (default).
+    burst_size: usize,
+    /// CLAW_BURST_REST_MS: default burst_rest_ms (default 15000, at most
+    /// MAX_BURST_REST_MS).
+    burst_rest_ms: u64,
+    /// CLAW_DELAY_FLOOR_MS: lowest min/max_delay_ms a request may ask for
+    /// (default CLAW_MIN_DELAY_MS: requests can slow a scrape, not speed it up).
+    delay_floor_ms: u64,
+    /// CLAW_DELAY_CEILING_MS: highest min/max_delay_ms a request may ask for,
+    /// so one scrape can't hold its slot for hours (default 60000, never below
+    /// CLAW_MAX_DELAY_MS or the floor).
+    delay_ceiling_ms: u64,
+    /// CLAW_BLOCK_COOLDOWN_SECS: how long a host that blocked a scrape is left
+    /// alone, doubling on each further block (default 60).
+    block_cooldown: Duration,
+    /// CLAW_BLOCK_COOLDOWN_MAX_SECS: longest cooldown; also how long without a
+    /// block it takes to forgive one strike (default 3600).
+    block_cooldown_max: Duration,
+    /// CLAW_BREAKER_WINDOW: recent page fetches per host the circuit breaker
+    /// looks at (default 20).
+    breaker_window: usize,
+    /// CLAW_BREAKER_MIN_FETCHES: outcomes needed before it may open (default 6).
+    breaker_min_fetches: usize,
+    /// CLAW_BREAKER_FAILURE_RATIO: failed share of the window that opens it (default 0.5).
+    breaker_failure_ratio: f64,
+    /// CLAW_BREAKER_OPEN_SECS: how long an open circuit fails fast before a
+    /// trial fetch (default 120).
+    breaker_open: Duration,
+    /// CLAW_POOL_MAX_IDLE_PER_HOST: idle connections kept per host (default 4).
+    pool_max_idle_per_host: usize,
+    /// CLAW_POOL_IDLE_TIMEOUT_SECS: drop idle connections after this long;
+    /// 0 keeps them forever (default 90).
+    pool_idle_timeout: Option<Duration>,
+    /// CLAW_TCP_KEEPALIVE_SECS: TCP keepalive interval, 0 disables (default 60).
+    tcp_keepalive: Option<Duration>,
+    /// CLAW_MAX_REDIRECTS: redirect hops a fetch follows before failing (default 8).
+    max_redirects: usize,
+    /// CLAW_ACCEPT_ANY_CONTENT_TYPE: judge pages by their body alone; by default
+    /// a response with a Content-Type other than HTML counts as a failed attempt.
+    accept_any_content_type: bool,
+    /// CLAW_NO_CARD_FALLBACK: trust only the structured EntityList path; by
+    /// default bare `li.EntityList-item`s are tried when it finds nothing.
+    no_card_fallback: bool,
+    /// CLAW_NO_WARMUP_TOKEN: keep only the warmup's cookies; by default an
+    /// anti-bot token the site adapter knows how to find in the warmup
+    /// response is sent back as a header on every page fetch.
+    no_warmup_token: bool,
+    /// CLAW_MIN_PAGE_BYTES: below this a page needs its marker as a class name
+    /// to be accepted (default 4000).
+    min_page_bytes: usize,
+    /// CLAW_PAGE_CACHE: keep fetched pages and revalidate them with
+    /// If-None-Match/If-Modified-Since; a 304 reuses the cached copy.
+    page_cache: bool,
+    /// CLAW_PAGE_CACHE_ENTRIES: pages the cache holds before evicting (default 500).
+    page_cache_entries: usize,
+    /// CLAW_SESSION_TTL_SECS: how long an unused dedup `session` keeps its
+    /// seen ids (default 1800).
+    session_ttl: Duration,
+    /// CLAW_LOCALE: formatting of price_display / price_per_m2_display (default hr-HR).
+    locale: NumberLocale,
+    /// CLAW_TRAILING_SLASH: `add` or `strip` a category path's trailing slash
+    /// before paging it, `keep` it as given (default).
+    trailing_slash: TrailingSlash,
+    /// CLAW_RECORD_DIR: save every accepted page's HTML here as a fixture.
+    record_dir: Option<PathBuf>,
+    /// CLAW_REPLAY_DIR: serve pages from fixtures here instead of the network,
+    /// when a matching file exists (robots.txt and warmup still go out).
+    replay_dir: Option<PathBuf>,
+    /// CLAW_API_TOKEN: bearer token the /admin endpoints require; unset leaves
+    /// them open, so set it anywhere the port is reachable by others.
+    api_token: Option<String>,
+    /// CLAW_HIT_PROCESSORS: comma-separated HitProcessor names to run on each
+    /// hit, in order (built-ins: convert_to_eur, convert_to_hrk); unset runs
+    /// every registered processor.
+    hit_processors: Option<Vec<String>>,
+    /// CLAW_PROFILES_PATH: JSON file of named search profiles for
+    /// /scrape/profile/{name}; re-read on every request.
+    profiles_path: Option<PathBuf>,
+    /// CLAW_DISTRICTS_PATH: JSON table of location substring → district that
+    /// fills PriceHit.district; read once at first use.
+    districts_path: Option<PathBuf>,
+}
+
+/// Dashboard form default when CLAW_DEFAULT_URL is unset.
+const DEFAULT_CATEGORY_URL: &str = "https://www.njuskalo.hr/prodaja-stanova/zagreb";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::from_env)
+}
+
+impl Config {
+    fn from_env() -> Self {
+        let accept_invalid_certs = env_flag("CLAW_DANGER_ACCEPT_INVALID_CERTS");
+        if accept_invalid_certs {
+            eprintln!(
+                "[config] WARNING: CLAW_DANGER_ACCEPT_INVALID_CERTS is set, TLS certificates are NOT verified"
+            );
+            eprintln!(
+                "[config] WARNING: this is for local test fixtures only, never enable it in production"
+            );
+        }
+        let max_concurrent_scrapes = env_parse("CLAW_MAX_CONCURRENT_SCRAPES", 4).max(1);
+        let min_delay_ms = env_parse("CLAW_MIN_DELAY_MS", PAGE_DELAY_MS.start);
+        let max_delay_ms = env_parse("CLAW_MAX_DELAY_MS", PAGE_DELAY_MS.end).max(min_delay_ms);
+        let delay_floor_ms = env_parse("CLAW_DELAY_FLOOR_MS", min_delay_ms);
+        Self {
+            accept_invalid_certs,
+            db_path: std::env::var("CLAW_DB_PATH").ok().filter(|p| !p.is_empty()),
+            fixed_header_order: env_flag("CLAW_FIXED_HEADER_ORDER"),
+            rng_seed: std::env::var("CLAW_RNG_SEED")
+                .ok()
+                .and_then(|v| v.trim().parse().ok()),
+            max_body_bytes: env_parse("CLAW_MAX_BODY_BYTES", 10 * 1024 * 1024),
+            workers: Some(env_parse("CLAW_WORKERS", 0)).filter(|n| *n > 0),
+            max_concurrent_scrapes,
+            default_url: std::env::var("CLAW_DEFAULT_URL")
+                .ok()
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .unwrap_or_else(|| DEFAULT_CATEGORY_URL.to_string()),
+            default_page_range: env_parse("CLAW_DEFAULT_PAGE_RANGE", 10).max(1),
+            base_path: normalize_base_path(&std::env::var("CLAW_BASE_PATH").unwrap_or_default()),
+            allowed_path_prefixes: env_list("CLAW_ALLOWED_PATH_PREFIXES"),
+            no_results_markers: env_list("CLAW_NO_RESULTS_MARKERS"),
+            card_link_selector: std::env::var("CLAW_CARD_LINK_SELECTOR")
+                .ok()
+                .filter(|s| !s.trim().is_empty()),
+            status_badge_selector: std::env::var("CLAW_STATUS_BADGE_SELECTOR")
+                .ok()
+                .filter(|s| !s.trim().is_empty()),
+            host_concurrency: env_parse("CLAW_HOST_CONCURRENCY", max_concurrent_scrapes).max(1),
+            host_queue_depth: env_parse("CLAW_HOST_QUEUE_DEPTH", 16),
+            client_host_concurrency: env_parse("CLAW_CLIENT_HOST_CONCURRENCY", 0),
+            trust_proxy: env_flag("CLAW_TRUST_PROXY"),
+            max_sse_streams: env_parse("CLAW_MAX_SSE_STREAMS", 16).max(1),
+            min_delay_ms,
+            max_delay_ms,
+            burst_size: env_parse("CLAW_BURST_SIZE", 0),
+            burst_rest_ms: env_parse("CLAW_BURST_REST_MS", 15_000).min(MAX_BURST_REST_MS),
+            delay_floor_ms,
+            delay_ceiling_ms: env_parse("CLAW_DELAY_CEILING_MS", 60_000)
+                .max(max_delay_ms)
+                .max(delay_floor_ms),
+            block_cooldown: Duration::from_secs(env_parse("CLAW_BLOCK_COOLDOWN_SECS", 60)),
+            block_cooldown_max: Duration::from_secs(env_parse(
+                "CLAW_BLOCK_COOLDOWN_MAX_SECS",
+                3600,
+            )),
+            breaker_window: env_parse("CLAW_BREAKER_WINDOW", 20).max(1),
+            breaker_min_fetches: env_parse("CLAW_BREAKER_MIN_FETCHES", 6).max(1),
+            breaker_failure_ratio: env_parse("CLAW_BREAKER_FAILURE_RATIO", 0.5),
+            breaker_open: Duration::from_secs(env_parse("CLAW_BREAKER_OPEN_SECS", 120)),
+            pool_max_idle_per_host: env_parse("CLAW_POOL_MAX_IDLE_PER_HOST", 4),
+            pool_idle_timeout: env_secs("CLAW_POOL_IDLE_TIMEOUT_SECS", 90),
+            tcp_keepalive: env_secs("CLAW_TCP_KEEPALIVE_SECS", 60),
+            max_redirects: env_parse("CLAW_MAX_REDIRECTS", 8),
+            accept_any_content_type: env_flag("CLAW_ACCEPT_ANY_CONTENT_TYPE"),
+            no_card_fallback: env_flag("CLAW_NO_CARD_FALLBACK"),
+            no_warmup_token: env_flag("CLAW_NO_WARMUP_TOKEN"),
+            min_page_bytes: env_parse("CLAW_MIN_PAGE_BYTES", 4000),
+            page_cache: env_flag("CLAW_PAGE_CACHE"),
+            page_cache_entries: env_parse("CLAW_PAGE_CACHE_ENTRIES", 500).max(1),
+            session_ttl: Duration::from_secs(env_parse("CLAW_SESSION_TTL_SECS", 1800)),
+            locale: {
+                let tag = std::env::var("CLAW_LOCALE").unwrap_or_else(|_| "hr-HR".to_string());
+                NumberLocale::from_tag(&tag).unwrap_or_else(|| {
+                    eprintln!(
+                        "[config] ignoring unsupported CLAW_LOCALE={:?}, using hr-HR",
+                        tag
+                    );
+                    NumberLocale::from_tag("hr-HR").expect("hr-HR is supported")
+                })
+            },
+            trailing_slash: {
+                let name = std::env::var("CLAW_TRAILING_SLASH").unwrap_or_default();
+                TrailingSlash::from_name(&name).unwrap_or_else(|| {
+                    eprintln!(
+                        "[config] ignoring invalid CLAW_TRAILING_SLASH={:?}, expected keep, add or strip",
+                        name
+                    );
+                    TrailingSlash::Keep
+                })
+            },
+            record_dir: env_path("CLAW_RECORD_DIR"),
+            replay_dir: env_path("CLAW_REPLAY_DIR"),
+            profiles_path: env_path("CLAW_PROFILES_PATH"),
+            districts_path: env_path("CLAW_DISTRICTS_PATH"),
+            hit_processors: std::env::var_os("CLAW_HIT_PROCESSORS")
+                .map(|_| env_list("CLAW_HIT_PROCESSORS")),
+            api_token: std::env::var("CLAW_API_TOKEN")
+                .ok()
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+        }
+    }
+}
+
+/// `claw/`, `/claw` and `/claw/` all become `/claw`; empty or `/` is the root.
+fn normalize_base_path(raw: &str) -> String {
+    let trimmed = raw.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+/// Parses an env var, falling back to `default` (with a warning) when unset or invalid.
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(v) => v.trim().parse().unwrap_or_else(|_| {
+            eprintln!("[config] ignoring invalid {}={:?}", name, v);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// Seconds from an env var; 0 means "off" (None).
+fn env_secs(name: &str, default: u64) -> Option<Duration> {
+    Some(env_parse(name, default))
+        .filter(|s| *s > 0)
+        .map(Duration::from_secs)
+}
+
+/// None (no deadline) also for a timeout too far out for the clock to hold.
+fn scrape_deadline(total_timeout_secs: Option<u64>) -> Option<tokio::time::Instant> {
+    tokio::time::Instant::now().checked_add(Duration::from_secs(total_timeout_secs?))
+}
+
+/// Resolves at the deadline, or never without one; a `select!` arm that cuts
+/// a scrape short.
+async fn until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(d) => tokio::time::sleep_until(d).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Comma-separated, trimmed, empty items dropped; unset is an empty list.
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Set and non-empty → a path.
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `1`, `true`, `yes` or `on` (any case) → true; unset or anything else → false.
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| {
+        matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
+// -------------------------
+// Misc helpers
+// -------------------------
+
+/// Current time as RFC 3339 UTC, e.g. `2025-01-31T09:15:02.123456Z`.
+fn now_rfc3339() -> String {
+    time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default()
+}
+
+const BUILTIN_DESKTOP_UAS: &[&str] = &[
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15",
+];
+
+const BUILTIN_MOBILE_UAS: &[&str] = &[
+    "Mozilla/5.0 (Linux; Android 14; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Mobile Safari/537.36",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+];
+
+struct UaPools {
+    desktop: Vec<String>,
+    mobile: Vec<String>,
+}
+
+static UA_POOLS: OnceLock<UaPools> = OnceLock::new();
+
+fn ua_pools() -> &'static UaPools {
+    UA_POOLS.get_or_init(load_ua_pools)
+}
+
+/// Reads `CLAW_UA_FILE` (one UA per line: `desktop|mobile <whitespace> <UA>`,
+/// `#` comments allowed). Either pool falls back to the built-in list when the
+/// file is unset, unreadable or has no entries for it.
+fn load_ua_pools() -> UaPools {
+    let mut desktop: Vec<String> = Vec::new();
+    let mut mobile: Vec<String> = Vec::new();
+
+    if let Ok(path) = std::env::var("CLAW_UA_FILE") {
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                for (n, line) in contents.lines().enumerate() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let Some((tag, ua)) = line.split_once(char::is_whitespace) else {
+                        eprintln!("[ua] {}:{}: missing UA after tag, skipped", path, n + 1);
+                        continue;
+                    };
+                    let ua = ua.trim();
+                    if HeaderValue::from_str(ua).is_err() {
+                        eprintln!("[ua] {}:{}: not a valid header value, skipped", path, n + 1);
+                        continue;
+                    }
+                    match tag {
+                        "desktop" => desktop.push(ua.to_string()),
+                        "mobile" => mobile.push(ua.to_string()),
+                        other => {
+                            eprintln!("[ua] {}:{}: unknown tag {:?}, skipped", path, n + 1, other)
+                        }
+                    }
+                }
+            }
+            Err(e) => eprintln!("[ua] cannot read CLAW_UA_FILE={}: {e}", path),
+        }
+    }
+
+    if desktop.is_empty() {
+        desktop = BUILTIN_DESKTOP_UAS.iter().map(|s| s.to_string()).collect();
+    }
+    if mobile.is_empty() {
+        mobile = BUILTIN_MOBILE_UAS.iter().map(|s| s.to_string()).collect();
+    }
+    UaPools { desktop, mobile }
+}
+
+fn random_desktop_ua() -> String {
+    let uas = &ua_pools().desktop;
+    let i = with_jitter_rng(|r| r.random_range(0..uas.len()));
+    uas[i].clone()
+}
+
+fn random_mobile_ua() -> String {
+    let uas = &ua_pools().mobile;
+    let i = with_jitter_rng(|r| r.random_range(0..uas.len()));
+    uas[i].clone()
+}
+
+static JITTER_RNG: OnceLock<Mutex<StdRng>> = OnceLock::new();
+
+/// RNG behind UA choice and header jitter. Seeded from CLAW_RNG_SEED when set,
+/// so header sets are reproducible in tests.
+fn with_jitter_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    let rng = JITTER_RNG.get_or_init(|| {
+        Mutex::new(match config().rng_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        })
+    });
+    let mut guard = rng.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
@@ -0,0 +1,106 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use claw::{ScrapeReq, next_cooldown, scrape_prices};
+use serde_json::json;
+
+/// Plain-HTTP proxy that serves an open robots.txt and answers every other
+/// request the way a rate limiter does: 429 with a JSON body.
+fn rate_limiting_proxy() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let (status, content_type, body) = if request_line.contains("/robots.txt ") {
+                ("200 OK", "text/plain", "User-agent: *\nAllow: /\n")
+            } else {
+                (
+                    "429 Too Many Requests",
+                    "application/json",
+                    r#"{"error":"rate limited"}"#,
+                )
+            };
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn json_429_ends_in_host_cooldown() {
+    let proxy = rate_limiting_proxy();
+    // the only test in this binary that touches the environment
+    unsafe {
+        std::env::set_var("HTTP_PROXY", &proxy);
+        std::env::set_var("http_proxy", &proxy);
+        std::env::remove_var("NO_PROXY");
+        std::env::remove_var("no_proxy");
+    }
+
+    let req: ScrapeReq = serde_json::from_value(json!({
+        "url": "http://njuskalo.hr/prodaja-stanova/zagreb",
+        "quick": true,
+    }))
+    .unwrap();
+    let Err(err) = scrape_prices(&req).await else {
+        panic!("a rate-limited page produced hits");
+    };
+    let msg = format!("{err:#}");
+    // the bare host's cooldown is the site's, under its canonical name
+    assert!(
+        msg.starts_with("www.njuskalo.hr is blocking scrapes"),
+        "{msg}"
+    );
+}
+
+#[test]
+fn cooldown_doubles_caps_and_decays() {
+    let base = Duration::from_secs(60);
+    let max = Duration::from_secs(3600);
+    let secs = |s| Duration::from_secs(s);
+
+    // first block, then one doubling per strike in a row
+    assert_eq!(next_cooldown(None, base, max), (1, secs(60)));
+    assert_eq!(next_cooldown(Some((1, secs(5))), base, max), (2, secs(120)));
+    assert_eq!(next_cooldown(Some((3, secs(5))), base, max), (4, secs(480)));
+
+    // capped at max, however many strikes pile up
+    assert_eq!(next_cooldown(Some((6, secs(5))), base, max), (7, max));
+    assert_eq!(
+        next_cooldown(Some((u32::MAX, secs(0))), base, max),
+        (u32::MAX, max)
+    );
+
+    // one strike forgiven per full quiet `max`, never below a fresh start
+    assert_eq!(
+        next_cooldown(Some((3, secs(3599))), base, max),
+        (4, secs(480))
+    );
+    assert_eq!(
+        next_cooldown(Some((3, secs(3600))), base, max),
+        (3, secs(240))
+    );
+    assert_eq!(
+        next_cooldown(Some((3, secs(7200))), base, max),
+        (2, secs(120))
+    );
+    assert_eq!(
+        next_cooldown(Some((3, secs(86400))), base, max),
+        (1, secs(60))
+    );
+}
@@ -0,0 +1,101 @@
+use claw::{build_page_url, normalize_pager};
+use url::Url;
+
+/// (category URL, page to build, expected page URL)
+const PAGE_URLS: &[(&str, usize, &str)] = &[
+    (
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb",
+        2,
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?page=2",
+    ),
+    (
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?page=3",
+        4,
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?page=4",
+    ),
+    (
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?price%5Bmax%5D=200000&sort=new",
+        2,
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?price%5Bmax%5D=200000&sort=new&page=2",
+    ),
+    (
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?sort=new&page=1&geo%5BlocationIds%5D=1153%2C1154",
+        2,
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?sort=new&page=2&geo%5BlocationIds%5D=1153%2C1154",
+    ),
+    (
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?q=stan+s+vrtom&page=2&page=5&flag",
+        3,
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?q=stan+s+vrtom&page=3&flag",
+    ),
+    (
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?q=%C5%A1ibenik&pages=9",
+        2,
+        "https://www.njuskalo.hr/prodaja-stanova/zagreb?q=%C5%A1ibenik&pages=9&page=2",
+    ),
+];
+
+#[test]
+fn build_page_url_keeps_other_params_verbatim() {
+    for (input, page, expected) in PAGE_URLS {
+        let (base, _) = normalize_pager(&Url::parse(input).unwrap());
+        let got = build_page_url(&base, *page).unwrap();
+        assert_eq!(got.as_str(), *expected, "page {page} of {input}");
+    }
+}
+
+#[test]
+fn normalize_pager_start_page() {
+    let cases = [
+        ("https://www.njuskalo.hr/prodaja-stanova/zagreb", 1),
+        (
+            "https://www.njuskalo.hr/prodaja-stanova/zagreb?page=7&sort=new",
+            7,
+        ),
+        ("https://www.njuskalo.hr/prodaja-stanova/zagreb?page=0", 1),
+        ("https://www.njuskalo.hr/prodaja-stanova/zagreb?page=x", 1),
+    ];
+    for (input, start) in cases {
+        let (_, got) = normalize_pager(&Url::parse(input).unwrap());
+        assert_eq!(got, start, "start page of {input}");
+    }
+}
+
+/// njuskalo's own search filters, as pasted from the browser.
+const FILTERED_URLS: &[&str] = &[
+    "https://www.njuskalo.hr/prodaja-stanova/zagreb?cijenaod=100000&cijenado=250000&povrsinaod=50&povrsinado=80",
+    "https://www.njuskalo.hr/prodaja-stanova/zagreb?page=3&cijenaod=100000&cijenado=250000",
+    "https://www.njuskalo.hr/prodaja-stanova/zagreb?geo%5BlocationIds%5D=1153%2C1154&povrsinaod=50&page=2&sort=cheap",
+    "https://www.njuskalo.hr/prodaja-stanova/zagreb?livingArea%5Bmin%5D=50&price%5Bmax%5D=250000&adsWithImages=1",
+];
+
+fn non_page_pairs(url: &Url) -> Vec<&str> {
+    url.query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|kv| !kv.is_empty() && *kv != "page" && !kv.starts_with("page="))
+        .collect()
+}
+
+#[test]
+fn site_filters_survive_every_page() {
+    for input in FILTERED_URLS {
+        let url = Url::parse(input).unwrap();
+        let filters = non_page_pairs(&url);
+        let (base, start) = normalize_pager(&url);
+        for page in start..start + 5 {
+            let page_url = build_page_url(&base, page).unwrap();
+            assert_eq!(
+                non_page_pairs(&page_url),
+                filters,
+                "filters of page {page} of {input}: {page_url}"
+            );
+            assert_eq!(
+                page_url.query_pairs().filter(|(k, _)| k == "page").count(),
+                1,
+                "{page_url}"
+            );
+            assert_eq!(page_url.path(), url.path(), "{page_url}");
+        }
+    }
+}
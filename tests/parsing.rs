@@ -0,0 +1,543 @@
+use claw::{
+    HitFilter, PriceHit, ScrapeReq, decode_html, extract_attributes, extract_id,
+    extract_sqm_from_li, extract_total_listings, load_district_table, lookup_district,
+    normalize_price, parse_listing_html, parse_posted_date, parse_sqm, token_source_for,
+    warmup_token,
+};
+use scraper::{Html, Selector};
+use serde_json::Value;
+use url::Url;
+
+/// (input, price, currency, flags)
+type PriceCase = (
+    &'static str,
+    Option<f64>,
+    Option<&'static str>,
+    &'static [&'static str],
+);
+
+const PRICES: &[PriceCase] = &[
+    ("185.000 €", Some(185000.0), Some("EUR"), &[]),
+    ("€ 99", Some(99.0), Some("EUR"), &[]),
+    ("1.250.000 €", Some(1250000.0), Some("EUR"), &[]),
+    ("1.250,50 €", Some(1250.5), Some("EUR"), &[]),
+    (
+        "Cijena: 245.000 € (fiksno)",
+        Some(245000.0),
+        Some("EUR"),
+        &[],
+    ),
+    ("1.921.297 kn", Some(1921297.0), Some("HRK"), &[]),
+    ("750.000 KN", Some(750000.0), Some("HRK"), &[]),
+    (
+        "185000",
+        Some(185000.0),
+        Some("EUR"),
+        &["currency_defaulted"],
+    ),
+    ("Po dogovoru", None, None, &["price_on_request"]),
+    ("Cijena na upit", None, None, &["price_on_request"]),
+    ("", None, None, &["price_on_request"]),
+];
+
+#[test]
+fn normalize_price_table() {
+    for (input, price, currency, flags) in PRICES {
+        let mut got_flags = Vec::new();
+        let (got_price, got_currency) = normalize_price(input, &mut got_flags);
+        assert_eq!(got_price, *price, "price of {input:?}");
+        assert_eq!(got_currency.as_deref(), *currency, "currency of {input:?}");
+        assert_eq!(got_flags, *flags, "flags of {input:?}");
+    }
+}
+
+/// (description, sqm, inferred)
+const SQMS: &[(&str, Option<f64>, bool)] = &[
+    ("Stambena površina: 58,00 m2", Some(58.0), false),
+    ("Stambena površina: 58 m²", Some(58.0), false),
+    ("55m2", Some(55.0), false),
+    ("55,5㎡", Some(55.5), false),
+    ("74 M2", Some(74.0), false),
+    ("55.5 m2", Some(55.5), false),
+    ("Površina: 1.234,5 m²", Some(1234.5), false),
+    ("3-sobni, 2. kat, 65 m2", Some(65.0), false),
+    ("Lokacija: Zagreb, 72", Some(72.0), true),
+    ("Lokacija: Zagreb, Trešnjevka", None, false),
+    ("", None, false),
+];
+
+#[test]
+fn parse_sqm_table() {
+    for (input, sqm, inferred) in SQMS {
+        let got = parse_sqm(input);
+        assert_eq!(got.map(|(v, _)| v), *sqm, "sqm of {input:?}");
+        if sqm.is_some() {
+            assert_eq!(
+                got.map(|(_, i)| i),
+                Some(*inferred),
+                "inferred of {input:?}"
+            );
+        }
+    }
+}
+
+/// Every unit spelling the sqm regex knows, each behind bare numbers that must
+/// not win over the number carrying the unit.
+#[test]
+fn sqm_unit_variants() {
+    for unit in ["m²", "m2", "㎡", "M²", "M2"] {
+        for sep in ["", " ", "\u{a0}", "  "] {
+            let input = format!("3-sobni, 2. kat, 61,5{sep}{unit}, 1990.");
+            assert_eq!(parse_sqm(&input), Some((61.5, false)), "{input:?}");
+        }
+    }
+    assert_eq!(parse_sqm("m2 nije naveden, 3. kat"), Some((3.0, true)));
+}
+
+#[test]
+fn extract_sqm_from_li_table() {
+    let desc = Selector::parse(".entity-description-main").unwrap();
+    let li = Selector::parse("li").unwrap();
+    for (input, sqm, _) in SQMS {
+        let html =
+            format!("<ul><li><div class=\"entity-description-main\">{input}</div></li></ul>");
+        let doc = Html::parse_fragment(&html);
+        let node = doc.select(&li).next().unwrap();
+        let got = extract_sqm_from_li(&node, &desc).map(|(v, _)| v);
+        assert_eq!(got, *sqm, "sqm of {input:?}");
+    }
+
+    let doc = Html::parse_fragment("<ul><li><div>58 m2</div></li></ul>");
+    let node = doc.select(&li).next().unwrap();
+    assert_eq!(extract_sqm_from_li(&node, &desc), None, "no description");
+}
+
+#[test]
+fn extract_attributes_list_markup() {
+    let desc = Selector::parse(".entity-description-main").unwrap();
+    let li = Selector::parse("li.card").unwrap();
+    let doc = Html::parse_fragment(
+        "<ul><li class=\"card\"><ul class=\"entity-description-main\">\
+         <li><span>Broj soba:</span> 3</li><li><span>Kat:</span>  Prizemlje </li>\
+         <li>Novogradnja</li></ul></li></ul>",
+    );
+    let node = doc.select(&li).next().unwrap();
+    let attrs = extract_attributes(&node, &desc);
+    assert_eq!(attrs.len(), 2, "{attrs:?}");
+    assert_eq!(attrs["Broj soba"], "3");
+    assert_eq!(attrs["Kat"], "Prizemlje");
+}
+
+#[test]
+fn extract_id_table() {
+    let cases = [
+        (
+            "https://www.njuskalo.hr/nekretnine/stan-zagreb-oglas-41234567",
+            "41234567",
+        ),
+        (
+            "https://www.njuskalo.hr/nekretnine/stan-oglas-41234567/",
+            "41234567",
+        ),
+        (
+            "https://www.njuskalo.hr/nekretnine/stan-oglas-41234567?page=2",
+            "41234567",
+        ),
+        (
+            "https://www.njuskalo.hr/nekretnine/stan-oglas-41234567#galerija",
+            "41234567",
+        ),
+        ("https://www.njuskalo.hr/oglas/41234567", "41234567"),
+        ("https://www.njuskalo.hr/nekretnine/stan-zagreb-2", ""),
+        ("https://www.njuskalo.hr/nekretnine/stan-zagreb?page=2", ""),
+        ("https://www.njuskalo.hr/nekretnine/stan-oglas-", ""),
+        ("", ""),
+    ];
+    for (url, id) in cases {
+        assert_eq!(extract_id(url), id, "id of {url:?}");
+    }
+
+    // the same listing under two slugs is one id
+    assert_eq!(
+        extract_id("https://www.njuskalo.hr/nekretnine/stan-zagreb-centar-2-sobni-oglas-41234567"),
+        extract_id("https://www.njuskalo.hr/nekretnine/stan-zagreb-donji-grad-oglas-41234567"),
+    );
+}
+
+#[test]
+fn short_trailing_digits_do_not_collapse_listings() {
+    let card = |href: &str| {
+        format!(
+            "<li class=\"EntityList-item\"><article class=\"entity-body\">\
+             <h3 class=\"entity-title\"><a class=\"link\" href=\"{href}\">Stan</a></h3>\
+             <div class=\"entity-prices\"><strong class=\"price price--eur\">150.000 €</strong></div>\
+             </article></li>"
+        )
+    };
+    let html = format!(
+        "<section class=\"EntityList\"><ul class=\"EntityList-items\">{}{}</ul></section>",
+        card("/nekretnine/stan-tresnjevka-2"),
+        card("/nekretnine/stan-dubrava-2"),
+    );
+    let page_url = Url::parse("https://www.njuskalo.hr/prodaja-stanova/zagreb").unwrap();
+    let hits = parse_listing_html(&html, &page_url).unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_ne!(hits[0].id, hits[1].id);
+    assert!(
+        hits.iter()
+            .all(|h| h.flags.iter().any(|f| f == "id_hashed"))
+    );
+}
+
+#[test]
+fn zagreb_district_table() {
+    let path = format!("{}/data/zagreb_districts.json", env!("CARGO_MANIFEST_DIR"));
+    let table = load_district_table(std::path::Path::new(&path)).unwrap();
+    let cases = [
+        ("Zagreb, Trešnjevka - sjever", Some("Trešnjevka - sjever")),
+        (
+            "Grad Zagreb, Trešnjevka - jug, Knežija",
+            Some("Trešnjevka - jug"),
+        ),
+        ("Zagreb, Gornja Dubrava, Čulinec", Some("Gornja Dubrava")),
+        ("Zagreb, Dubrava", Some("Donja Dubrava")),
+        ("ZAGREB, ŠPANSKO", Some("Stenjevec")),
+        (
+            "Stan, Zagreb, Jarun, 1-sobni, 35 m2",
+            Some("Trešnjevka - jug"),
+        ),
+        ("Split, Bačvice", None),
+    ];
+    for (location, district) in cases {
+        assert_eq!(lookup_district(location, &table), district, "{location:?}");
+    }
+}
+
+fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read_to_string(&path).unwrap()
+}
+
+fn fixture_hits(name: &str) -> Vec<Value> {
+    let html = fixture(name);
+    let page_url = Url::parse("https://www.njuskalo.hr/prodaja-stanova/zagreb").unwrap();
+    parse_listing_html(&html, &page_url)
+        .unwrap()
+        .iter()
+        .map(|hit| serde_json::to_value(hit).unwrap())
+        .collect()
+}
+
+#[test]
+fn discounted_listing_fixture() {
+    let hits = fixture_hits("discounted_listing.html");
+    assert_eq!(hits.len(), 2);
+
+    assert_eq!(hits[0]["id"], "41234567");
+    assert_eq!(hits[0]["price_numeric"], 199000.0);
+    assert_eq!(hits[0]["original_price"], 215000.0);
+    assert_eq!(hits[0]["discount_pct"], 7.4);
+    assert_eq!(hits[0]["sqm"], 58.0);
+    assert_eq!(hits[0]["sqm_confidence"], 0.8);
+    assert_eq!(hits[0]["price_confidence"], 0.8);
+    assert_eq!(hits[0]["rooms"], 2.5);
+    assert_eq!(hits[0]["floor"], "3. kat");
+    assert_eq!(
+        hits[0]["attributes"],
+        serde_json::json!({
+            "Stambena površina": "58,00 m2",
+            "Lokacija": "Zagreb, Trešnjevka - sjever",
+            "Broj soba": "2,5",
+            "Kat": "3. kat",
+        })
+    );
+
+    assert_eq!(hits[1]["price_numeric"], 289000.0);
+    assert_eq!(hits[1]["original_price"], Value::Null);
+    assert_eq!(hits[1]["sqm"], 74.0);
+    assert_eq!(hits[1]["rooms"], Value::Null);
+}
+
+#[test]
+fn data_price_listing_fixture() {
+    let hits = fixture_hits("data_price_listing.html");
+    assert_eq!(hits.len(), 4);
+
+    let expected = [
+        (245000.0, "price_from_attribute", 1.0, false),
+        (112500.0, "price_from_attribute", 1.0, false),
+        (255000.0, "price_from_text", 0.8, false),
+        (99000.0, "price_from_attribute", 1.0, true),
+    ];
+    for (hit, (price, source, confidence, defaulted)) in hits.iter().zip(expected) {
+        let flags = hit["flags"].as_array().unwrap();
+        assert_eq!(
+            flags.iter().any(|f| f == "currency_defaulted"),
+            defaulted,
+            "{} flags {}",
+            hit["id"],
+            hit["flags"]
+        );
+        assert_eq!(hit["price_numeric"], price, "{}", hit["id"]);
+        assert_eq!(hit["price_confidence"], confidence, "{}", hit["id"]);
+        assert_eq!(hit["currency"], "EUR", "{}", hit["id"]);
+        assert!(
+            flags.iter().any(|f| f == source),
+            "{} flags {}",
+            hit["id"],
+            hit["flags"]
+        );
+    }
+}
+
+#[test]
+fn rental_listing_fixture() {
+    let hits = fixture_hits("rental_listing.html");
+    assert_eq!(hits.len(), 3);
+
+    assert_eq!(hits[0]["price_numeric"], 850.0);
+    assert_eq!(hits[0]["price_period"], "monthly");
+    assert_eq!(hits[0]["price_per_m2"], Value::Null);
+
+    assert_eq!(hits[1]["price_numeric"], 600.0);
+    assert_eq!(hits[1]["price_period"], "monthly");
+    assert_eq!(hits[1]["price_per_m2"], Value::Null);
+
+    assert_eq!(hits[2]["price_period"], Value::Null);
+    assert_eq!(hits[2]["price_per_m2"], 3000.0);
+}
+
+#[test]
+fn reserved_listing_fixture() {
+    let hits = fixture_hits("reserved_listing.html");
+    let statuses: Vec<&str> = hits.iter().map(|h| h["status"].as_str().unwrap()).collect();
+    assert_eq!(statuses, ["active", "reserved", "sold"]);
+    assert_eq!(hits[1]["price_numeric"], 195000.0);
+
+    let hits = fixture_hits("discounted_listing.html");
+    assert!(hits.iter().all(|h| h["status"] == "active"));
+}
+
+#[test]
+fn parse_posted_date_table() {
+    let cases = [
+        ("2025-03-14", Some("2025-03-14")),
+        ("2025-03-14T09:12:00+01:00", Some("2025-03-14")),
+        ("14.03.2025.", Some("2025-03-14")),
+        ("Objavljen: 2. 3. 2025.", Some("2025-03-02")),
+        ("31.02.2025.", None),
+        ("jučer", None),
+        ("", None),
+    ];
+    for (input, expected) in cases {
+        let got = parse_posted_date(input).map(|d| d.to_string());
+        assert_eq!(got.as_deref(), expected, "date of {input:?}");
+    }
+}
+
+#[test]
+fn dated_listing_fixture() {
+    let hits = fixture_hits("dated_listing.html");
+    let dates: Vec<Option<&str>> = hits.iter().map(|h| h["posted_date"].as_str()).collect();
+    assert_eq!(dates, [Some("2025-03-14"), Some("2025-03-02"), None]);
+}
+
+fn since_filter(since: &str, strict: bool) -> anyhow::Result<HitFilter> {
+    let req: ScrapeReq = serde_json::from_value(serde_json::json!({
+        "url": "https://www.njuskalo.hr/prodaja-stanova/zagreb",
+        "since": since,
+        "strict_since": strict,
+    }))
+    .unwrap();
+    HitFilter::new(&req)
+}
+
+fn dated_hits() -> Vec<PriceHit> {
+    let page_url = Url::parse("https://www.njuskalo.hr/prodaja-stanova/zagreb").unwrap();
+    parse_listing_html(&fixture("dated_listing.html"), &page_url).unwrap()
+}
+
+#[test]
+fn since_filter_table() {
+    // hits posted 2025-03-14, 2025-03-02 and undated, in that order
+    let hits = dated_hits();
+    let cases = [
+        ("", false, [true, true, true]),
+        ("2025-03-02", false, [true, true, true]),
+        ("2025-03-03", false, [true, false, true]),
+        ("2025-03-03", true, [true, false, false]),
+        ("2025-03-14", true, [true, false, false]),
+        ("2025-03-15", false, [false, false, true]),
+        ("2025-03-15", true, [false, false, false]),
+        ("14.03.2025.", true, [true, false, false]),
+    ];
+    for (since, strict, expected) in cases {
+        let filter = since_filter(since, strict).unwrap();
+        let kept: Vec<bool> = hits.iter().map(|h| filter.matches(h)).collect();
+        assert_eq!(kept, expected, "since={since:?} strict={strict}");
+    }
+
+    for bad in ["2025-02-31", "yesterday"] {
+        let err = since_filter(bad, false).err().expect(bad);
+        assert!(err.to_string().contains("invalid since"), "{err}");
+    }
+}
+
+#[test]
+fn since_cutoff_needs_every_card_dated_and_older() {
+    let hits = dated_hits();
+    let dated = &hits[..2];
+
+    assert!(
+        since_filter("2025-03-15", false)
+            .unwrap()
+            .page_predates_since(dated)
+    );
+    // the undated card might be recent, so the page doesn't end the scrape
+    assert!(
+        !since_filter("2025-03-15", false)
+            .unwrap()
+            .page_predates_since(&hits)
+    );
+    // on the boundary a card is not older
+    assert!(
+        !since_filter("2025-03-14", false)
+            .unwrap()
+            .page_predates_since(dated)
+    );
+    assert!(
+        !since_filter("2025-03-15", false)
+            .unwrap()
+            .page_predates_since(&[])
+    );
+    assert!(!since_filter("", false).unwrap().page_predates_since(dated));
+}
+
+#[test]
+fn total_listings_fixture() {
+    let doc = Html::parse_document(&fixture("discounted_listing.html"));
+    assert_eq!(extract_total_listings(&doc), Some(1234));
+
+    let doc = Html::parse_document(&fixture("rental_listing.html"));
+    assert_eq!(extract_total_listings(&doc), None);
+}
+
+#[test]
+fn windows1250_listing_fixture() {
+    let path = format!(
+        "{}/tests/fixtures/windows1250_listing.html",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(
+        std::str::from_utf8(&bytes).is_err(),
+        "fixture must not be UTF-8"
+    );
+    let page_url = Url::parse("https://www.njuskalo.hr/prodaja-stanova/zagreb").unwrap();
+    let titles = |html: String| -> Vec<String> {
+        parse_listing_html(&html, &page_url)
+            .unwrap()
+            .iter()
+            .map(|hit| {
+                serde_json::to_value(hit).unwrap()["title"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    };
+    let expected = [
+        "Stan, Zagreb, Črnomerec, 2-sobni, 54 m2",
+        "Stan, Zagreb, Trešnjevka, đački dom, 38 m2",
+    ];
+
+    // <meta http-equiv> declaration
+    assert_eq!(titles(decode_html(&bytes, None)), expected);
+    // Content-Type header charset
+    assert_eq!(
+        titles(decode_html(&bytes, Some("text/html; charset=windows-1250"))),
+        expected
+    );
+    // nothing declared: invalid UTF-8 falls back to windows-1250
+    let undeclared: Vec<u8> = bytes
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.starts_with(b"  <meta http-equiv"))
+        .collect::<Vec<_>>()
+        .join(&b'\n');
+    assert_eq!(
+        titles(decode_html(&undeclared, Some("text/html"))),
+        expected
+    );
+
+    // the header parameter is matched like the meta tag: any case, spaces around `=`
+    let latin2 = b"<p>Tre\xb9njevka</p>";
+    for ct in [
+        "text/html; Charset=ISO-8859-2",
+        "text/html;CHARSET = \"iso-8859-2\"",
+    ] {
+        assert_eq!(decode_html(latin2, Some(ct)), "<p>Trešnjevka</p>", "{ct}");
+    }
+
+    // UTF-8 stays UTF-8
+    let utf8 = "<p>Trešnjevka</p>".as_bytes();
+    assert_eq!(decode_html(utf8, None), "<p>Trešnjevka</p>");
+}
+
+#[test]
+fn fallback_cards_stay_in_main_region() {
+    let card = |id: u32, title: &str| {
+        format!(
+            "<li class=\"EntityList-item\"><article class=\"entity-body\">\
+             <h3 class=\"entity-title\"><a class=\"link\" href=\"/nekretnine/stan-oglas-{id}\">{title}</a></h3>\
+             <div class=\"entity-prices\"><strong class=\"price price--eur\">150.000 €</strong></div>\
+             </article></li>"
+        )
+    };
+    let html = format!(
+        "<html><body><nav><ul>{}</ul></nav><main><ul>{}{}</ul></main></body></html>",
+        card(1, "Izbornik"),
+        card(2, "Stan A"),
+        card(3, "Stan B"),
+    );
+    let page_url = Url::parse("https://www.njuskalo.hr/prodaja-stanova/zagreb").unwrap();
+    let ids: Vec<String> = parse_listing_html(&html, &page_url)
+        .unwrap()
+        .iter()
+        .map(|hit| hit.id.clone())
+        .collect();
+    assert_eq!(ids, ["2", "3"]);
+}
+
+#[test]
+fn warmup_csrf_token() {
+    let source = token_source_for("www.njuskalo.hr");
+    assert!(source.is_some(), "njuskalo has a TokenSource");
+
+    let body = r#"<html><head><meta charset="utf-8">
+        <meta name="csrf-token" content=" a1B2-c3_d4= "></head><body></body></html>"#;
+    let token = warmup_token(source, body).unwrap();
+    assert_eq!(token.header.as_str(), "x-csrf-token");
+    assert_eq!(token.value.to_str().unwrap(), "a1B2-c3_d4=");
+
+    let single_quoted = "<meta name='csrf-token' content='xyz'>";
+    assert_eq!(
+        warmup_token(source, single_quoted).map(|t| t.value),
+        Some("xyz".parse().unwrap())
+    );
+
+    // missing, empty, oversized and header-unsafe tokens are all dropped
+    assert!(warmup_token(source, "<html><head></head></html>").is_none());
+    assert!(warmup_token(source, r#"<meta name="csrf-token" content="  ">"#).is_none());
+    let oversized = format!(r#"<meta name="csrf-token" content="{}">"#, "a".repeat(513));
+    assert!(warmup_token(source, &oversized).is_none());
+    let at_limit = format!(r#"<meta name="csrf-token" content="{}">"#, "a".repeat(512));
+    assert!(warmup_token(source, &at_limit).is_some());
+    assert!(warmup_token(source, "<meta name=\"csrf-token\" content=\"a\u{7}b\">").is_none());
+}
+
+#[test]
+fn warmup_token_needs_a_source() {
+    let body = r#"<meta name="csrf-token" content="abc">"#;
+    assert!(warmup_token(None, body).is_none());
+    assert!(token_source_for("www.example.com").is_none());
+}
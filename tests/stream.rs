@@ -0,0 +1,86 @@
+use actix_web::http::StatusCode;
+use claw::{accepts_encoding, csv_event_rows, sse_error_event, stream_error_status};
+use serde_json::{Value, json};
+
+#[test]
+fn sse_error_escapes_quotes_and_newlines() {
+    let message = "bad \"url\"\nsecond line\r\nthird";
+    let bytes = sse_error_event("fetch_failed", message);
+    let text = std::str::from_utf8(&bytes).unwrap();
+
+    assert!(text.ends_with("\n\n"), "{text:?}");
+    assert!(!text.contains('\r'), "{text:?}");
+    let lines: Vec<&str> = text.trim_end_matches('\n').split('\n').collect();
+    assert_eq!(lines.len(), 2, "{text:?}");
+    assert_eq!(lines[0], "event: error");
+
+    let data = lines[1].strip_prefix("data: ").unwrap();
+    let payload: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(payload["code"], "fetch_failed");
+    assert_eq!(payload["message"], message);
+}
+
+#[test]
+fn csv_rows_for_page_and_hits_events() {
+    let hits = json!([
+        {"id": "111", "page": 1, "title": "Stan, \"lux\"", "price_numeric": 100000.0, "flags": ["a", "b"]},
+        {"id": "222", "page": 1, "title": "Stan B", "price_numeric": null, "flags": []}
+    ]);
+    for event in ["page", "hits"] {
+        let data = json!({ "page": 1, "hits": hits }).to_string();
+        let bytes = csv_event_rows(event, &data).unwrap();
+        let text = std::str::from_utf8(&bytes).unwrap();
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 2, "{event}: {text:?}");
+        assert!(text.ends_with('\n'), "{event}: {text:?}");
+        assert!(rows[0].starts_with("111,1,"), "{event}: {}", rows[0]);
+        assert!(
+            rows[0].contains(r#""Stan, ""lux""""#),
+            "{event}: {}",
+            rows[0]
+        );
+        assert!(rows[0].ends_with(",a;b"), "{event}: {}", rows[0]);
+        assert!(rows[1].starts_with("222,1,"), "{event}: {}", rows[1]);
+    }
+}
+
+#[test]
+fn csv_rows_skip_other_events() {
+    let hits = json!({ "hits": [{"id": "111"}] }).to_string();
+    for event in ["start", "progress", "timeout", "done", "error"] {
+        assert_eq!(csv_event_rows(event, &hits), None, "{event}");
+    }
+    assert_eq!(csv_event_rows("page", r#"{"hits": []}"#), None);
+    assert_eq!(csv_event_rows("page", r#"{"count": 0}"#), None);
+    assert_eq!(csv_event_rows("page", "not json"), None);
+}
+
+#[test]
+fn stream_error_status_table() {
+    let cases = [
+        ("host_cooldown", StatusCode::TOO_MANY_REQUESTS),
+        ("circuit_open", StatusCode::SERVICE_UNAVAILABLE),
+        ("config", StatusCode::INTERNAL_SERVER_ERROR),
+        ("internal", StatusCode::INTERNAL_SERVER_ERROR),
+        ("invalid_url", StatusCode::BAD_REQUEST),
+        ("domain_not_allowed", StatusCode::BAD_REQUEST),
+        ("robots_disallowed", StatusCode::BAD_REQUEST),
+        ("fetch_failed", StatusCode::BAD_REQUEST),
+    ];
+    for (code, status) in cases {
+        assert_eq!(stream_error_status(code), status, "{code}");
+    }
+}
+
+#[test]
+fn explicit_zero_quality_beats_wildcard() {
+    assert!(!accepts_encoding("br;q=0, *", "br"));
+    assert!(!accepts_encoding("*, BR; q=0", "br"));
+    assert!(!accepts_encoding("br;Q=0, *", "br"));
+    assert!(!accepts_encoding("br; q = 0, *", "br"));
+    assert!(accepts_encoding("br;q=0, *", "gzip"));
+    assert!(!accepts_encoding("gzip, *;q=0", "br"));
+    assert!(accepts_encoding("gzip;q=0.5, br", "br"));
+    assert!(!accepts_encoding("gzip", "br"));
+    assert!(!accepts_encoding("", "gzip"));
+}